@@ -66,5 +66,6 @@ ibus_eei_engine_enable  (IBusEngine *engine)
 {
     // dummy call to tell the input context that the engine will utilize surrounding-text
     ibus_engine_get_surrounding_text (engine, NULL, NULL, NULL);
+    ibus_eei_engine_check_dictionary (engine);
 }
 
@@ -19,6 +19,20 @@ static const GOptionEntry entries[] =
     { NULL },
 };
 
+/* one engine per dictionary locale, which the engine core derives from the name
+   (must match eei.xml) */
+static const struct {
+    const gchar *name;
+    const gchar *longname;
+    const gchar *language;
+    const gchar *layout;
+} engines[] =
+{
+    { "eei", "Extended English Input", "en", "us" },
+    { "eei-en-GB", "Extended English Input (British English)", "en_GB", "gb" },
+    { "eei-de-DE", "Extended English Input (German)", "de", "de" },
+};
+
 static void
 ibus_disconnected_cb (IBusBus  *bus,
                       gpointer  user_data)
@@ -38,7 +52,9 @@ init (void)
 	
     factory = ibus_factory_new (ibus_bus_get_connection (bus));
     g_object_ref_sink (factory);
-    ibus_factory_add_engine (factory, "eei", IBUS_TYPE_EEI_ENGINE);
+    for (guint i = 0; i < G_N_ELEMENTS (engines); i++) {
+        ibus_factory_add_engine (factory, engines[i].name, IBUS_TYPE_EEI_ENGINE);
+    }
 
     if (ibus) {
         ibus_bus_request_name (bus, "joshua.tanner.IBus.eei", 0); //hangs if name doesn't match eei.xml
@@ -54,15 +70,17 @@ init (void)
                                         "https://github.com/Mindful/eei",
                                         "",
                                         "ibus-eei");
-        ibus_component_add_engine (component,
-                                   ibus_engine_desc_new ("eei",
-                                                         "Extended English Input",
-                                                         "Extended English Input",
-                                                         "en",
-                                                         "GPL",
-                                                         "Joshua Tanner <mindful.jt@gmail.com>",
-                                                         PKGDATADIR"/icons/ibus-enchant.svg",
-                                                         "us"));
+        for (guint i = 0; i < G_N_ELEMENTS (engines); i++) {
+            ibus_component_add_engine (component,
+                                       ibus_engine_desc_new (engines[i].name,
+                                                             engines[i].longname,
+                                                             engines[i].longname,
+                                                             engines[i].language,
+                                                             "GPL",
+                                                             "Joshua Tanner <mindful.jt@gmail.com>",
+                                                             PKGDATADIR"/icons/ibus-enchant.svg",
+                                                             engines[i].layout));
+        }
         ibus_bus_register_component (bus, component);
     }
 }
@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
+use std::error;
+use std::fmt::{Display, Formatter};
+
+/// Every data bundle starts with this, followed by the format version
+pub const MAGIC: [u8; 8] = *b"EEIDATA\0";
+pub const FORMAT_VERSION: u32 = 1;
+
+pub const SYMBOLS_BUNDLE: &str = "symbols.bundle";
+pub const SHORTCODES_SECTION: &str = "shortcodes";
+pub const SYMBOLS_SECTION: &str = "symbols";
+pub const DICTIONARY_SECTION: &str = "dictionary";
+
+/// Optional data generated from large sources and installed to the data directory, each with a
+/// single FST section
+pub const UNICODE_NAMES_BUNDLE: &str = "unicode_names.bundle";
+pub const NAMES_SECTION: &str = "names";
+pub const LATEX_COMMANDS_BUNDLE: &str = "latex_commands.bundle";
+pub const COMMANDS_SECTION: &str = "commands";
+
+/// The locale of the dictionary built into the engine
+pub const DEFAULT_LOCALE: &str = "en_US";
+
+/// Dictionaries are generated per locale, e.g. `dictionary-en_GB.bundle`
+pub fn dictionary_bundle(locale: &str) -> String {
+    format!("dictionary-{}.bundle", locale)
+}
+
+/// Domain word packs, e.g. `pack-medical.bundle`, are extra dictionaries layered on top of the
+/// locale's dictionary. They have a single dictionary section like the locale dictionaries.
+pub fn pack_bundle(name: &str) -> String {
+    format!("pack-{}.bundle", name)
+}
+
+/// Common phrases for a locale, e.g. `phrases-en_US.bundle`, which are completed alongside single
+/// words. They have a single dictionary section mapping each phrase to its count.
+pub fn phrases_bundle(locale: &str) -> String {
+    format!("phrases-{}.bundle", locale)
+}
+
+//magic, then the version and metadata length as little endian u32s
+const PREFIX_LENGTH: usize = MAGIC.len() + 4 + 4;
+
+/// An input file the bundle was generated from, so bundles can be traced back to their sources
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SourceInfo {
+    pub name: String,
+    pub crc32: u32,
+}
+
+impl SourceInfo {
+    pub fn new(name: &str, contents: &[u8]) -> SourceInfo {
+        SourceInfo {
+            name: name.to_string(),
+            crc32: crc32fast::hash(contents),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SectionInfo {
+    pub name: String,
+    pub length: u64,
+    pub crc32: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BundleMetadata {
+    pub sources: Vec<SourceInfo>,
+    pub sections: Vec<SectionInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleError {
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    InvalidMetadata(String),
+    ChecksumMismatch(String),
+    MissingSection(String),
+}
+
+impl Display for BundleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BundleError::InvalidMagic => write!(f, "Not an EEI data bundle"),
+            BundleError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported data format version {} (expected {})",
+                version, FORMAT_VERSION
+            ),
+            BundleError::Truncated => write!(f, "Data bundle is truncated"),
+            BundleError::InvalidMetadata(err) => write!(f, "Invalid bundle metadata: {}", err),
+            BundleError::ChecksumMismatch(section) => {
+                write!(f, "Checksum mismatch for section {}", section)
+            }
+            BundleError::MissingSection(section) => write!(f, "Missing section {}", section),
+        }
+    }
+}
+
+impl error::Error for BundleError {}
+
+/// A parsed data bundle, with every section's length and checksum already validated
+pub struct Bundle<'a> {
+    pub version: u32,
+    pub metadata: BundleMetadata,
+    sections: Vec<&'a [u8]>,
+}
+
+impl<'a> Bundle<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Bundle<'a>, BundleError> {
+        if bytes.len() < PREFIX_LENGTH {
+            return Err(if bytes.starts_with(&MAGIC) || MAGIC.starts_with(bytes) {
+                BundleError::Truncated
+            } else {
+                BundleError::InvalidMagic
+            });
+        }
+        if bytes[..MAGIC.len()] != MAGIC {
+            return Err(BundleError::InvalidMagic);
+        }
+
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let version = read_u32(MAGIC.len());
+        if version != FORMAT_VERSION {
+            return Err(BundleError::UnsupportedVersion(version));
+        }
+
+        let metadata_length = read_u32(MAGIC.len() + 4) as usize;
+        let metadata_bytes = PREFIX_LENGTH
+            .checked_add(metadata_length)
+            .and_then(|end| bytes.get(PREFIX_LENGTH..end))
+            .ok_or(BundleError::Truncated)?;
+        let metadata: BundleMetadata = bincode::deserialize(metadata_bytes)
+            .map_err(|err| BundleError::InvalidMetadata(err.to_string()))?;
+
+        let mut offset = PREFIX_LENGTH + metadata_length;
+        let mut sections = Vec::with_capacity(metadata.sections.len());
+        for section in metadata.sections.iter() {
+            //lengths come from the file, so they may be too large to add
+            let end = usize::try_from(section.length)
+                .ok()
+                .and_then(|length| offset.checked_add(length))
+                .ok_or(BundleError::Truncated)?;
+            let data = bytes.get(offset..end).ok_or(BundleError::Truncated)?;
+            if crc32fast::hash(data) != section.crc32 {
+                return Err(BundleError::ChecksumMismatch(section.name.clone()));
+            }
+            sections.push(data);
+            offset = end;
+        }
+
+        Ok(Bundle {
+            version,
+            metadata,
+            sections,
+        })
+    }
+
+    pub fn section(&self, name: &str) -> Result<&'a [u8], BundleError> {
+        self.metadata
+            .sections
+            .iter()
+            .position(|section| section.name == name)
+            .map(|idx| self.sections[idx])
+            .ok_or_else(|| BundleError::MissingSection(name.to_string()))
+    }
+}
+
+/// Builds a bundle from named sections, recording the sources they were generated from
+pub fn write_bundle(sources: Vec<SourceInfo>, sections: &[(&str, &[u8])]) -> Vec<u8> {
+    let metadata = BundleMetadata {
+        sources,
+        sections: sections
+            .iter()
+            .map(|(name, data)| SectionInfo {
+                name: name.to_string(),
+                length: data.len() as u64,
+                crc32: crc32fast::hash(data),
+            })
+            .collect(),
+    };
+    let metadata_bytes =
+        bincode::serialize(&metadata).expect("Bundle metadata is always serializable");
+
+    let mut bytes = Vec::with_capacity(
+        PREFIX_LENGTH
+            + metadata_bytes.len()
+            + sections
+                .iter()
+                .map(|(_name, data)| data.len())
+                .sum::<usize>(),
+    );
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(metadata_bytes.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&metadata_bytes);
+    for (_name, data) in sections {
+        bytes.extend_from_slice(data);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bundle::{
+        write_bundle, Bundle, BundleError, BundleMetadata, SectionInfo, SourceInfo, FORMAT_VERSION,
+        MAGIC,
+    };
+
+    #[test]
+    fn round_trip() {
+        let bytes = write_bundle(
+            vec![SourceInfo::new("source.txt", b"source")],
+            &[("first", b"abc"), ("second", b"")],
+        );
+        let bundle = Bundle::parse(&bytes).unwrap();
+        assert_eq!(bundle.version, FORMAT_VERSION);
+        assert_eq!(bundle.metadata.sources[0].name, "source.txt");
+        assert_eq!(bundle.section("first"), Ok(&b"abc"[..]));
+        assert_eq!(bundle.section("second"), Ok(&b""[..]));
+        assert_eq!(
+            bundle.section("third").err(),
+            Some(BundleError::MissingSection("third".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_bundles() {
+        let bytes = write_bundle(vec![], &[("data", b"abc")]);
+        assert_eq!(Bundle::parse(b"").err(), Some(BundleError::Truncated));
+        assert_eq!(
+            Bundle::parse(b"not a bundle").err(),
+            Some(BundleError::InvalidMagic)
+        );
+        assert_eq!(
+            Bundle::parse(&bytes[..bytes.len() - 1]).err(),
+            Some(BundleError::Truncated)
+        );
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() = b'x';
+        assert_eq!(
+            Bundle::parse(&corrupted).err(),
+            Some(BundleError::ChecksumMismatch("data".to_string()))
+        );
+
+        let mut future = bytes;
+        future[8] = 2;
+        assert_eq!(
+            Bundle::parse(&future).err(),
+            Some(BundleError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn oversized_section() {
+        let metadata = bincode::serialize(&BundleMetadata {
+            sources: vec![],
+            sections: vec![SectionInfo {
+                name: "data".to_string(),
+                length: u64::MAX,
+                crc32: 0,
+            }],
+        })
+        .unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&metadata);
+        bytes.extend_from_slice(b"abc");
+        assert_eq!(Bundle::parse(&bytes).err(), Some(BundleError::Truncated));
+    }
+}
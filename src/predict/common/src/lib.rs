@@ -0,0 +1,4 @@
+pub mod bundle;
+pub mod symbols;
+pub mod unicode;
+pub mod words;
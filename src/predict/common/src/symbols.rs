@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::error;
+use std::fmt::{Display, Formatter};
+
+/// Separates a search keyword from the shortcode it belongs to in the keys of the shortcode FST,
+/// since unlike shortcodes the same keyword can be shared by several symbols
+pub const KEYWORD_SEPARATOR: char = '\0';
+
+const MAX_COLUMNS: usize = 6;
+
+/// Everything stored about a symbol in the generated symbol data
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    /// Every shortcode and alias for the symbol, highest priority source first
+    pub shortcodes: Vec<String>,
+}
+
+impl SymbolInfo {
+    pub fn new(symbol: String) -> SymbolInfo {
+        SymbolInfo {
+            symbol,
+            ..SymbolInfo::default()
+        }
+    }
+
+    /// The symbol's other names, for a result found under `shortcode`
+    pub fn aliases<'a>(&'a self, shortcode: &'a str) -> impl Iterator<Item = &'a String> {
+        self.shortcodes
+            .iter()
+            .filter(move |alias| alias.as_str() != shortcode)
+    }
+}
+
+/// The generated symbol data. Values in the shortcode FST index into `key_symbols`, since a
+/// shortcode can belong to several symbols, ranked by the priority of the sources defining them.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SymbolData {
+    pub symbols: Vec<SymbolInfo>,
+    pub key_symbols: Vec<Vec<u32>>,
+}
+
+pub fn keyword_key(keyword: &str, shortcode: &str) -> String {
+    format!("{}{}{}", keyword, KEYWORD_SEPARATOR, shortcode)
+}
+
+/// The shortcode a key in the shortcode FST belongs to, which is the key itself unless it's a
+/// keyword key
+pub fn key_shortcode(key: &str) -> &str {
+    key.split_once(KEYWORD_SEPARATOR)
+        .map(|(_keyword, shortcode)| shortcode)
+        .unwrap_or(key)
+}
+
+/// A shortcode and everything else known about its symbol. In custom shortcode files each entry
+/// is a line of `shortcode<tab>symbol[<tab>keywords[<tab>category[<tab>description[<tab>aliases]]]]`,
+/// where keywords and aliases are comma separated and any optional column may be left empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortcodeEntry {
+    pub shortcode: String,
+    pub symbol: String,
+    pub keywords: Vec<String>,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub aliases: Vec<String>,
+}
+
+impl ShortcodeEntry {
+    pub fn new(shortcode: String, symbol: String) -> ShortcodeEntry {
+        ShortcodeEntry {
+            shortcode,
+            symbol,
+            keywords: Vec::new(),
+            category: None,
+            description: None,
+            aliases: Vec::new(),
+        }
+    }
+
+    pub fn info(&self) -> SymbolInfo {
+        SymbolInfo {
+            symbol: self.symbol.clone(),
+            category: self.category.clone(),
+            description: self.description.clone(),
+            shortcodes: self.shortcodes().cloned().collect(),
+        }
+    }
+
+    /// The shortcode followed by its aliases
+    pub fn shortcodes(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.shortcode).chain(self.aliases.iter())
+    }
+
+    /// Sorted keys for the shortcode, its aliases and its keywords and category
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .shortcodes()
+            .cloned()
+            .chain(
+                self.keywords
+                    .iter()
+                    .chain(self.category.iter())
+                    .map(|keyword| keyword_key(keyword, &self.shortcode)),
+            )
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortcodeParseError {
+    /// 1-based line number
+    pub line: usize,
+    pub reason: String,
+}
+
+impl Display for ShortcodeParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl error::Error for ShortcodeParseError {}
+
+fn optional_column(column: Option<&str>) -> Option<String> {
+    column
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn list_column(column: Option<&str>) -> Vec<String> {
+    column
+        .map(|list| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn validate_name(kind: &str, name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        Err(format!("empty {}", kind))
+    } else if name.contains(char::is_whitespace) || name.contains(KEYWORD_SEPARATOR) {
+        Err(format!("{} {:?} contains whitespace", kind, name))
+    } else {
+        Ok(())
+    }
+}
+
+/// Parses a single line, returning `None` for blank lines and `#` comments
+pub fn parse_shortcode_entry(line: &str) -> Result<Option<ShortcodeEntry>, String> {
+    if line.trim().is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < 2 {
+        return Err("expected a shortcode and a symbol separated by a tab".to_string());
+    } else if columns.len() > MAX_COLUMNS {
+        return Err(format!(
+            "expected at most {} columns, found {}",
+            MAX_COLUMNS,
+            columns.len()
+        ));
+    }
+
+    let shortcode = columns[0].trim();
+    validate_name("shortcode", shortcode)?;
+    if columns[1].is_empty() {
+        return Err(format!("empty symbol for shortcode {:?}", shortcode));
+    }
+
+    let entry = ShortcodeEntry {
+        shortcode: shortcode.to_string(),
+        symbol: columns[1].to_string(),
+        keywords: list_column(columns.get(2).copied()),
+        category: optional_column(columns.get(3).copied()),
+        description: optional_column(columns.get(4).copied()),
+        aliases: list_column(columns.get(5).copied()),
+    };
+    for alias in entry.aliases.iter() {
+        validate_name("alias", alias)?;
+    }
+    Ok(Some(entry))
+}
+
+/// Parses every line of a custom shortcode file, with errors for the lines that are invalid
+pub fn parse_shortcode_entries(
+    contents: &str,
+) -> impl Iterator<Item = Result<ShortcodeEntry, ShortcodeParseError>> + '_ {
+    contents.lines().enumerate().filter_map(|(idx, line)| {
+        parse_shortcode_entry(line)
+            .map_err(|reason| ShortcodeParseError {
+                line: idx + 1,
+                reason,
+            })
+            .transpose()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::symbols::{key_shortcode, parse_shortcode_entries, ShortcodeEntry};
+
+    #[test]
+    fn shortcode_entries() {
+        let contents = "pien\t🥹\n\
+                        # comment\n\
+                        \n\
+                        lenny\t( ͡° ͜ʖ ͡°)\tface, meme\tface\tLenny face\tlenny_face\n\
+                        shrug\t¯\\_(ツ)_/¯\t\t\tShrug\n\
+                        broken\n\
+                        a b\tc\n\
+                        x\ty\t\t\t\tgood, bad alias";
+        let parsed: Vec<_> = parse_shortcode_entries(contents).collect();
+
+        assert_eq!(
+            parsed[0],
+            Ok(ShortcodeEntry {
+                shortcode: "pien".to_string(),
+                symbol: "🥹".to_string(),
+                keywords: vec![],
+                category: None,
+                description: None,
+                aliases: vec![],
+            })
+        );
+        let lenny = parsed[1].as_ref().unwrap();
+        assert_eq!(lenny.symbol, "( ͡° ͜ʖ ͡°)");
+        assert_eq!(lenny.description.as_deref(), Some("Lenny face"));
+        assert_eq!(
+            lenny.keys(),
+            vec!["face\0lenny", "lenny", "lenny_face", "meme\0lenny"]
+        );
+        assert_eq!(
+            parsed[2].as_ref().unwrap().description.as_deref(),
+            Some("Shrug")
+        );
+        assert_eq!(parsed[3].as_ref().unwrap_err().line, 6);
+        assert_eq!(parsed[4].as_ref().unwrap_err().line, 7);
+        assert_eq!(
+            parsed[5].as_ref().unwrap_err().to_string(),
+            "line 8: alias \"bad alias\" contains whitespace"
+        );
+
+        assert_eq!(key_shortcode("meme\0lenny"), "lenny");
+        assert_eq!(key_shortcode("lenny"), "lenny");
+    }
+}
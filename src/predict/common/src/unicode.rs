@@ -0,0 +1,90 @@
+use std::error;
+use std::fmt::{Display, Formatter};
+use std::num::ParseIntError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnicodeParseError {
+    Hex(ParseIntError),
+    Codepoint(u32),
+}
+
+impl Display for UnicodeParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnicodeParseError::Hex(err) => write!(f, "Invalid hex value: {}", err),
+            UnicodeParseError::Codepoint(codepoint) => {
+                write!(f, "Invalid codepoint: U+{:04X}", codepoint)
+            }
+        }
+    }
+}
+
+impl error::Error for UnicodeParseError {}
+
+pub const MAX_CODEPOINT: u32 = 0x10FFFF;
+
+//https://stackoverflow.com/questions/69152223/unicode-codepoint-to-rust-string
+pub fn parse_unicode(input: &str) -> Result<char, UnicodeParseError> {
+    let unicode = u32::from_str_radix(input, 16).map_err(UnicodeParseError::Hex)?;
+    char::from_u32(unicode).ok_or(UnicodeParseError::Codepoint(unicode))
+}
+
+/// Parses a whitespace separated sequence of hex codepoints (optionally prefixed with `U+`) into
+/// a single string, so that multi-codepoint sequences like ZWJ emoji can be built
+pub fn parse_codepoints(input: &str) -> Result<String, UnicodeParseError> {
+    input
+        .split_whitespace()
+        .map(|codepoint| {
+            parse_unicode(
+                codepoint
+                    .strip_prefix("U+")
+                    .or_else(|| codepoint.strip_prefix("u+"))
+                    .unwrap_or(codepoint),
+            )
+        })
+        .collect()
+}
+
+/// Separates the name suffix being searched from the full character name in the keys of the
+/// unicode name FST
+pub const NAME_SEPARATOR: char = '\0';
+
+/// Byte offsets of every word in a character name, so that names can be searched from any word
+pub fn name_word_starts(name: &str) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(0).chain(
+        name.match_indices(is_name_word_separator)
+            .map(|(idx, separator)| idx + separator.len()),
+    )
+}
+
+pub fn is_name_word_separator(c: char) -> bool {
+    c == ' ' || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unicode::{parse_codepoints, parse_unicode, UnicodeParseError};
+
+    #[test]
+    fn single_codepoints() {
+        assert_eq!(parse_unicode("3b1"), Ok('α'));
+        assert_eq!(parse_unicode("1F600"), Ok('😀'));
+        assert_eq!(
+            parse_unicode("D800"),
+            Err(UnicodeParseError::Codepoint(0xD800))
+        );
+        assert!(matches!(
+            parse_unicode("xyz"),
+            Err(UnicodeParseError::Hex(_))
+        ));
+    }
+
+    #[test]
+    fn codepoint_sequences() {
+        assert_eq!(
+            parse_codepoints("1F468 200D U+1F469").as_deref(),
+            Ok("👨\u{200D}👩")
+        );
+        assert_eq!(parse_codepoints("").as_deref(), Ok(""));
+    }
+}
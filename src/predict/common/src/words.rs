@@ -0,0 +1,70 @@
+use std::error;
+use std::fmt::{Display, Formatter};
+
+/// Count given to words listed without one, roughly that of a fairly common English word in the
+/// built-in dictionary so that they're suggested without crowding out everything else
+pub const DEFAULT_WORD_COUNT: u64 = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordParseError {
+    /// 1-based line number
+    pub line: usize,
+    pub reason: String,
+}
+
+impl Display for WordParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl error::Error for WordParseError {}
+
+/// Parses a line of a word list, which is a word optionally followed by a tab and its count.
+/// Returns `None` for blank lines and `#` comments. Words are lowercased like the dictionary.
+pub fn parse_word_count(line: &str) -> Result<Option<(String, u64)>, String> {
+    if line.trim().is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let mut columns = line.split('\t');
+    let word = columns.next().unwrap_or("").trim();
+    if word.is_empty() {
+        return Err("empty word".to_string());
+    }
+    let count = match columns.next().map(str::trim) {
+        Some(count) if !count.is_empty() => count
+            .parse::<u64>()
+            .map_err(|err| format!("invalid count {:?} for {}: {}", count, word, err))?,
+        _ => DEFAULT_WORD_COUNT,
+    };
+    Ok(Some((word.to_lowercase(), count)))
+}
+
+/// Parses every line of a word list, with errors for the lines that are invalid
+pub fn parse_word_counts(
+    contents: &str,
+) -> impl Iterator<Item = Result<(String, u64), WordParseError>> + '_ {
+    contents.lines().enumerate().filter_map(|(idx, line)| {
+        parse_word_count(line)
+            .map_err(|reason| WordParseError {
+                line: idx + 1,
+                reason,
+            })
+            .transpose()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::words::{parse_word_counts, DEFAULT_WORD_COUNT};
+
+    #[test]
+    fn word_counts() {
+        let parsed: Vec<_> =
+            parse_word_counts("# comment\nKubernetes\t5000\n\nrustc\nbad\tcount\n").collect();
+        assert_eq!(parsed[0], Ok(("kubernetes".to_string(), 5000)));
+        assert_eq!(parsed[1], Ok(("rustc".to_string(), DEFAULT_WORD_COUNT)));
+        assert_eq!(parsed[2].as_ref().unwrap_err().line, 5);
+    }
+}
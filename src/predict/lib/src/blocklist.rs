@@ -0,0 +1,77 @@
+use crate::config::Strictness;
+use std::collections::HashSet;
+
+/// Words that are never suggested as completions, though they can still be typed by hand
+#[derive(Default, Debug)]
+pub struct Blocklist {
+    words: HashSet<String>,
+    /// Entries ending in `*`, which block every word starting with them
+    prefixes: Vec<String>,
+}
+
+impl Blocklist {
+    /// Parses a blocklist with a word on each line, optionally followed by a tab and its level
+    /// (`strong` or `mild`, strong if left out). Only the words blocked at `strictness` are kept.
+    pub fn parse(contents: &str, strictness: Strictness) -> Blocklist {
+        let mut blocklist = Blocklist::default();
+        if strictness == Strictness::Off {
+            return blocklist;
+        }
+
+        for line in contents.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.split('\t').map(str::trim);
+            let word = columns.next().unwrap_or("").to_lowercase();
+            let mild = match columns.next() {
+                None | Some("") | Some("strong") => false,
+                Some("mild") => true,
+                Some(level) => {
+                    log::warn!("Unknown blocklist level {:?} for {}", level, word);
+                    false
+                }
+            };
+            if mild && strictness != Strictness::Strict {
+                continue;
+            }
+
+            match word.strip_suffix('*') {
+                Some(prefix) if !prefix.is_empty() => blocklist.prefixes.push(prefix.to_string()),
+                Some(_) => log::warn!("Ignoring blocklist entry that would block every word"),
+                None => {
+                    blocklist.words.insert(word);
+                }
+            }
+        }
+        blocklist
+    }
+
+    /// Whether a lowercase word is blocked
+    pub fn is_blocked(&self, word: &str) -> bool {
+        self.words.contains(word)
+            || self
+                .prefixes
+                .iter()
+                .any(|prefix| word.starts_with(prefix.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocklist::Blocklist;
+    use crate::config::Strictness;
+
+    #[test]
+    fn strictness() {
+        let contents = "# comment\ndamn\tmild\nfrak*\nheck\tstrong\n";
+        let normal = Blocklist::parse(contents, Strictness::Normal);
+        assert!(normal.is_blocked("frakking"));
+        assert!(normal.is_blocked("heck"));
+        assert!(!normal.is_blocked("hecks"));
+        assert!(!normal.is_blocked("damn"));
+
+        assert!(Blocklist::parse(contents, Strictness::Strict).is_blocked("damn"));
+        assert!(!Blocklist::parse(contents, Strictness::Off).is_blocked("frak"));
+    }
+}
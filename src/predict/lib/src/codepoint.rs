@@ -0,0 +1,72 @@
+use common::unicode::{parse_codepoints, parse_unicode, MAX_CODEPOINT};
+
+const MAX_HEX_DIGITS: usize = 6;
+
+/// Whether `next` can be appended to the codepoint preedit. Anything that could never become a
+/// valid codepoint (non-hex characters, values above U+10FFFF) is rejected as it's typed.
+pub fn accepts(preedit: &str, next: char) -> bool {
+    if next == ' ' {
+        return !preedit.is_empty() && !preedit.ends_with(' ');
+    }
+
+    let current = preedit.rsplit(' ').next().unwrap_or("");
+    next.is_ascii_hexdigit()
+        && current.len() < MAX_HEX_DIGITS
+        && u32::from_str_radix(&format!("{}{}", current, next), 16)
+            .map(|codepoint| codepoint <= MAX_CODEPOINT)
+            .unwrap_or(false)
+}
+
+/// The glyphs for the codepoints typed so far, skipping any that aren't (yet) valid
+pub fn preview(preedit: &str) -> String {
+    preedit
+        .split_whitespace()
+        .filter_map(|codepoint| parse_unicode(codepoint).ok())
+        .collect()
+}
+
+/// Auxiliary text listing the typed codepoints in U+XXXX form
+pub fn describe(preedit: &str) -> String {
+    let mut description = preedit
+        .split_whitespace()
+        .map(|codepoint| format!("U+{}", codepoint.to_ascii_uppercase()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if preedit.is_empty() {
+        description.push_str("U+");
+    } else if preedit.ends_with(' ') {
+        description.push_str(" U+");
+    }
+
+    if let Err(err) = parse_codepoints(preedit) {
+        description.push_str(&format!(" ({})", err));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codepoint::{accepts, describe, preview};
+
+    #[test]
+    fn validation() {
+        assert!(accepts("", 'f'));
+        assert!(!accepts("", ' '));
+        assert!(!accepts("", 'g'));
+        assert!(accepts("10FFF", 'F'));
+        assert!(!accepts("10FFFF", '0'));
+        assert!(!accepts("11000", '0'));
+        assert!(accepts("1F468", ' '));
+        assert!(!accepts("1F468 ", ' '));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(preview("1f468 200d 1f469"), "👨\u{200D}👩");
+        assert_eq!(preview("D800 3b1"), "α");
+        assert_eq!(describe(""), "U+");
+        assert_eq!(describe("3b1 "), "U+3B1 U+");
+        assert_eq!(describe("d800"), "U+D800 (Invalid codepoint: U+D800)");
+    }
+}
@@ -0,0 +1,130 @@
+use crate::paths::config_dir;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+static CONFIG_FILE: &str = "config.toml";
+
+/// User settings, read once from `$XDG_CONFIG_HOME/eei/config.toml`. Every setting has a default,
+/// so the file and any of its sections or keys may be left out.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub smart_punctuation: SmartPunctuationConfig,
+    pub emoticons: EmoticonsConfig,
+    pub dictionaries: DictionariesConfig,
+    pub blocklist: BlocklistConfig,
+    pub autocorrect: AutocorrectConfig,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct SmartPunctuationConfig {
+    pub enabled: bool,
+    /// `--` to —
+    pub dashes: bool,
+    /// `...` to …
+    pub ellipsis: bool,
+    /// Straight quotes and apostrophes to curly ones
+    pub quotes: bool,
+    /// `->`, `<-`, `=>` and `<=` to arrows
+    pub arrows: bool,
+}
+
+/// Converting emoticons like `:)` to emoji when they're followed by a space or punctuation
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct EmoticonsConfig {
+    pub enabled: bool,
+}
+
+/// Weights of the dictionary layers word completions are merged from. A word's score is its count
+/// times the weight of its layer, and a weight of 0 turns a layer off.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct DictionariesConfig {
+    /// The locale's dictionary
+    pub base_weight: f64,
+    /// Words from `words.tsv` in the config directory
+    pub user_weight: f64,
+    /// The locale's phrases, if they're installed
+    pub phrase_weight: f64,
+    /// Installed word packs by name, each with a weight of 1 unless given here
+    pub packs: HashMap<String, f64>,
+}
+
+/// Correcting common typos when a space is typed after them
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct AutocorrectConfig {
+    pub enabled: bool,
+}
+
+/// Keeping offensive words out of word completions
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct BlocklistConfig {
+    pub strictness: Strictness,
+}
+
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+    /// Nothing is blocked
+    Off,
+    /// Slurs and strong profanity are blocked
+    #[default]
+    Normal,
+    /// Mild profanity is blocked too
+    Strict,
+}
+
+impl Default for DictionariesConfig {
+    fn default() -> Self {
+        DictionariesConfig {
+            base_weight: 1.0,
+            user_weight: 1.0,
+            phrase_weight: 1.0,
+            packs: HashMap::new(),
+        }
+    }
+}
+
+impl Default for SmartPunctuationConfig {
+    fn default() -> Self {
+        SmartPunctuationConfig {
+            enabled: false,
+            dashes: true,
+            ellipsis: true,
+            quotes: true,
+            arrows: true,
+        }
+    }
+}
+
+impl Config {
+    fn load() -> Config {
+        let path = match config_dir() {
+            Ok(dir) => dir.join(CONFIG_FILE),
+            Err(err) => {
+                log::warn!("Could not locate config directory: {}", err);
+                return Config::default();
+            }
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+                log::error!("Invalid config in {:?}, using defaults: {}", path, err);
+                Config::default()
+            }),
+            Err(err) => {
+                log::info!("No config loaded from {:?}: {}", path, err);
+                Config::default()
+            }
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref CONFIG: Config = Config::load();
+}
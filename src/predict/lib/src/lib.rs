@@ -1,6 +1,16 @@
 #![allow(non_upper_case_globals)]
 #![allow(clippy::missing_safety_doc)]
+mod blocklist;
+mod codepoint;
+mod config;
+mod math;
+mod paths;
 mod predict;
+mod snippet;
+mod spelling;
+mod typography;
+mod user_store;
+mod watched;
 
 use log::LevelFilter;
 use log4rs::config::{Appender, Config, Root};
@@ -8,23 +18,31 @@ use log4rs::encode::pattern::PatternEncoder;
 use std::ffi::{CStr, CString, NulError};
 use std::os::raw::{c_char, c_int};
 
+use crate::config::CONFIG;
+use crate::math::MathStyle;
 use crate::predict::PREDICTOR;
+use common::bundle::DEFAULT_LOCALE;
+use common::symbols::SymbolInfo;
+use common::unicode::parse_codepoints;
 use ibus::{
-    gboolean, gchar, gint, guint, gunichar, ibus_engine_commit_text,
+    gboolean, gchar, gint, guint, ibus_engine_commit_text, ibus_engine_delete_surrounding_text,
+    ibus_engine_forward_key_event, ibus_engine_get_name, ibus_engine_get_surrounding_text,
     ibus_engine_hide_auxiliary_text, ibus_engine_hide_lookup_table, ibus_engine_hide_preedit_text,
     ibus_engine_update_auxiliary_text, ibus_engine_update_lookup_table,
-    ibus_engine_update_preedit_text, ibus_lookup_table_append_candidate, ibus_lookup_table_clear,
-    ibus_lookup_table_cursor_down, ibus_lookup_table_cursor_up, ibus_lookup_table_get_candidate,
-    ibus_lookup_table_get_cursor_in_page, ibus_lookup_table_get_cursor_pos,
-    ibus_lookup_table_get_label, ibus_lookup_table_get_number_of_candidates,
-    ibus_lookup_table_page_down, ibus_lookup_table_page_up, ibus_lookup_table_set_label,
-    ibus_text_append_attribute, ibus_text_get_length, ibus_text_new_from_static_string,
-    ibus_text_new_from_string, ibus_text_new_from_unichar, IBUS_BackSpace, IBUS_Down, IBUS_Escape,
-    IBUS_Left, IBUS_Page_Down, IBUS_Page_Up, IBUS_Return, IBUS_Right, IBUS_Up, IBUS_asciitilde,
-    IBUS_e, IBUS_space, IBUS_w, IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE,
-    IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE, IBusEEIEngine, IBusEngine, IBusEngineClass,
-    IBusLookupTable, IBusModifierType_IBUS_CONTROL_MASK, IBusModifierType_IBUS_SHIFT_MASK,
-    IBusText, GBOOL_FALSE, GBOOL_TRUE,
+    ibus_engine_update_preedit_text, ibus_keyval_to_unicode, ibus_lookup_table_append_candidate,
+    ibus_lookup_table_clear, ibus_lookup_table_cursor_down, ibus_lookup_table_cursor_up,
+    ibus_lookup_table_get_candidate, ibus_lookup_table_get_cursor_in_page,
+    ibus_lookup_table_get_cursor_pos, ibus_lookup_table_get_label,
+    ibus_lookup_table_get_number_of_candidates, ibus_lookup_table_page_down,
+    ibus_lookup_table_page_up, ibus_lookup_table_set_label, ibus_text_append_attribute,
+    ibus_text_get_length, ibus_text_new_from_static_string, ibus_text_new_from_string,
+    ibus_text_new_from_unichar, IBUS_BackSpace, IBUS_Down, IBUS_Escape, IBUS_Left, IBUS_Page_Down,
+    IBUS_Page_Up, IBUS_Return, IBUS_Right, IBUS_Tab, IBUS_Up, IBUS_a, IBUS_asciitilde, IBUS_b,
+    IBUS_backslash, IBUS_d, IBUS_e, IBUS_exclam, IBUS_m, IBUS_r, IBUS_space, IBUS_u, IBUS_w,
+    IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE, IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE,
+    IBusCapabilite_IBUS_CAP_SURROUNDING_TEXT, IBusEEIEngine, IBusEngine, IBusEngineClass,
+    IBusLookupTable, IBusModifierType_IBUS_CONTROL_MASK, IBusModifierType_IBUS_RELEASE_MASK,
+    IBusModifierType_IBUS_SHIFT_MASK, IBusText, GBOOL_FALSE, GBOOL_TRUE,
 };
 use lazy_static::lazy_static;
 use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
@@ -33,8 +51,9 @@ use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
 use log4rs::append::rolling_file::RollingFileAppender;
 use log4rs::filter::threshold::ThresholdFilter;
 use std::cmp::min;
-use std::path::Path;
 use InputMode::*;
+use SymbolSearch::*;
+use WordSearch::*;
 
 lazy_static! {
     static ref empty_cstring: CString = CString::new("").unwrap();
@@ -45,15 +64,58 @@ enum InputMode {
     Normal,
     SymbolTable,
     WordTable,
+    Codepoint,
+    MathCommand,
+    MathScript,
+}
+
+#[derive(PartialEq)]
+enum SymbolSearch {
+    Shortcode,
+    Name,
+}
+
+/// Whether the word table completes the word being typed or offers spelling corrections for it
+#[derive(PartialEq)]
+enum WordSearch {
+    Completion,
+    Correction,
+}
+
+/// Text that was automatically replaced while typing, kept so that a backspace right after the
+/// replacement can restore what was actually typed
+struct Conversion {
+    original: String,
+    replacement: String,
+    /// The word that was autocorrected, which reverting stops from being autocorrected again
+    autocorrected: Option<String>,
+}
+
+/// What selecting a labelled candidate does
+#[derive(Clone, PartialEq)]
+enum SymbolAction {
+    Insert,
+    Style(MathStyle),
+    Snippet(String),
 }
 
 pub struct EngineCore {
     table_visible: bool,
     word_buffer: String,
+    /// Parentheses opened in the words typed before `word_buffer` and not yet closed
+    open_parentheses: usize,
+    word_search: WordSearch,
     input_mode: InputMode,
     symbol_preedit: String,
+    symbol_search: SymbolSearch,
     symbol_label_vec: Vec<CString>,
+    symbol_action_vec: Vec<SymbolAction>,
     symbol_last_page: guint,
+    codepoint_preedit: String,
+    math_mode: bool,
+    math_preedit: String,
+    math_style: Option<MathStyle>,
+    last_conversion: Option<Conversion>,
     parent_engine: *mut IBusEEIEngine,
     parent_engine_class: *mut IBusEngineClass,
 }
@@ -66,20 +128,74 @@ pub unsafe extern "C" fn new_engine_core(
     Box::into_raw(Box::new(EngineCore {
         table_visible: false,
         word_buffer: String::new(),
+        open_parentheses: 0,
+        word_search: Completion,
         input_mode: InputMode::Normal,
         symbol_preedit: String::new(),
+        symbol_search: Shortcode,
         symbol_label_vec: Vec::new(),
+        symbol_action_vec: Vec::new(),
         symbol_last_page: 0,
+        codepoint_preedit: String::new(),
+        math_mode: false,
+        math_preedit: String::new(),
+        math_style: None,
+        last_conversion: None,
         parent_engine,
         parent_engine_class,
     }))
 }
 
+/// The dictionary locale for an engine: `eei` uses the default locale, and engines named like
+/// `eei-en-GB` use the locale in their name
+fn engine_locale(engine_name: &str) -> String {
+    engine_name
+        .strip_prefix("eei-")
+        .map(|locale| locale.replace('-', "_"))
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+/// Splits off the punctuation typed after a word, like the comma in "teh,", so that only the word
+/// itself is corrected
+fn split_trailing(typed: &str) -> (&str, &str) {
+    let end = typed.trim_end_matches(|c: char| !c.is_alphabetic()).len();
+    typed.split_at(end)
+}
+
+/// Autocorrects the word in `typed`, keeping any punctuation typed after it. Returns the word that
+/// was corrected and the text to replace `typed` with
+fn autocorrect_typed(
+    typed: &str,
+    autocorrection: impl Fn(&str) -> Option<String>,
+) -> Option<(&str, String)> {
+    let (word, trailing) = split_trailing(typed);
+    if word.is_empty() {
+        return None;
+    }
+    autocorrection(word).map(|correction| (word, correction + trailing))
+}
+
 unsafe fn into_ibus_string(input: String) -> Result<*mut IBusText, NulError> {
     CString::new(input.into_bytes())
         .map(|cstr| ibus_text_new_from_string(cstr.into_raw() as *const gchar))
 }
 
+/// Symbol table text for a symbol found under `shortcode`, listing its other names and description
+fn symbol_candidate_text(shortcode: &str, info: &SymbolInfo) -> String {
+    let mut text = shortcode.to_string();
+    let aliases: Vec<&str> = info
+        .aliases(shortcode)
+        .map(|alias| alias.as_str())
+        .collect();
+    if !aliases.is_empty() {
+        text.push_str(&format!(" [{}]", aliases.join(", ")));
+    }
+    if let Some(description) = &info.description {
+        text.push_str(&format!(" ({})", description));
+    }
+    text
+}
+
 impl EngineCore {
     /*
      ** General Methods **
@@ -112,6 +228,9 @@ impl EngineCore {
                 self.word_buffer.clear();
                 self.word_table_disable()
             }
+            Codepoint => self.codepoint_disable(),
+            MathCommand => self.math_command_disable(),
+            MathScript => self.math_script_disable(),
             Normal => GBOOL_FALSE,
         }
     }
@@ -126,6 +245,18 @@ impl EngineCore {
                 self.word_commit(idx);
                 GBOOL_TRUE
             }
+            Codepoint => {
+                self.codepoint_commit();
+                GBOOL_TRUE
+            }
+            MathCommand => {
+                self.math_command_commit(idx);
+                GBOOL_TRUE
+            }
+            MathScript => {
+                self.math_script_convert();
+                GBOOL_TRUE
+            }
             Normal => GBOOL_FALSE,
         };
         self.word_buffer.clear();
@@ -136,6 +267,15 @@ impl EngineCore {
         self.parent_engine as *mut IBusEngine
     }
 
+    unsafe fn locale(&self) -> String {
+        let name = ibus_engine_get_name(self.parent_engine_as_ibus_engine());
+        if name.is_null() {
+            DEFAULT_LOCALE.to_string()
+        } else {
+            engine_locale(&CStr::from_ptr(name).to_string_lossy())
+        }
+    }
+
     unsafe fn get_table(&self) -> *mut IBusLookupTable {
         (*self.parent_engine).table
     }
@@ -145,11 +285,196 @@ impl EngineCore {
     }
 
     unsafe fn commit_char(&mut self, keyval: guint) {
-        self.word_buffer.push((keyval as u8) as char);
+        let unichar = ibus_keyval_to_unicode(keyval);
+        self.word_buffer.extend(char::from_u32(unichar));
         ibus_engine_commit_text(
             self.parent_engine_as_ibus_engine(),
-            ibus_text_new_from_unichar(keyval as gunichar),
+            ibus_text_new_from_unichar(unichar),
+        );
+    }
+
+    /// Whether the client can delete text before the cursor, which replacing typed text needs.
+    /// Without it the replacement would be added after the typed text instead.
+    unsafe fn can_replace_preceding(&self) -> bool {
+        (*self.parent_engine_as_ibus_engine()).client_capabilities
+            & IBusCapabilite_IBUS_CAP_SURROUNDING_TEXT
+            != 0
+    }
+
+    /// Forget what was typed, like after the focus or cursor has moved somewhere else, so that
+    /// nothing is converted or reverted at the wrong position
+    fn clear_typed(&mut self) {
+        self.word_buffer.clear();
+        self.open_parentheses = 0;
+        self.last_conversion = None;
+    }
+
+    /// Finish the word being typed, keeping track of the parentheses it opened or closed
+    fn end_word(&mut self) {
+        for c in self.word_buffer.chars() {
+            match c {
+                '(' => self.open_parentheses += 1,
+                ')' => self.open_parentheses = self.open_parentheses.saturating_sub(1),
+                _ => {}
+            }
+        }
+        self.word_buffer.clear();
+    }
+
+    /// Replace the last `delete` characters before the cursor with `text`
+    unsafe fn replace_preceding(&mut self, delete: usize, text: &str) {
+        if delete > 0 {
+            ibus_engine_delete_surrounding_text(
+                self.parent_engine_as_ibus_engine(),
+                -(delete as gint),
+                delete as guint,
+            );
+            for _ in 0..delete {
+                self.word_buffer.pop();
+            }
+        }
+        self.word_buffer.push_str(text);
+        self.commit_string(text.to_string());
+    }
+
+    /// Replace the last `delete` characters and the character being typed with `replacement`,
+    /// remembering the conversion so it can be reverted
+    unsafe fn convert_typed(&mut self, delete: usize, keyval: guint, replacement: &str) {
+        let mut original: String = self
+            .word_buffer
+            .chars()
+            .rev()
+            .take(delete)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+        original.push((keyval as u8) as char);
+
+        self.replace_preceding(delete, replacement);
+        self.last_conversion = Some(Conversion {
+            original,
+            replacement: replacement.to_string(),
+            autocorrected: None,
+        });
+    }
+
+    /// Undo a conversion, restoring the text as it was typed
+    unsafe fn revert_conversion(&mut self, conversion: Conversion) {
+        log::info!(
+            "Reverting conversion of {} to {}",
+            conversion.original,
+            conversion.replacement
         );
+        self.replace_preceding(conversion.replacement.chars().count(), &conversion.original);
+        if conversion.original.ends_with(' ') {
+            self.word_buffer.clear();
+        }
+        if let Some(word) = conversion.autocorrected {
+            if let Err(err) = user_store::add_autocorrect_exception(&word) {
+                log::error!("Failed to save autocorrect exception for {}: {}", word, err);
+            }
+            PREDICTOR.reload_user_words();
+        }
+    }
+
+    /// Autocorrect the word just typed, now that it's been ended by a space
+    unsafe fn autocorrect_convert(&mut self, keyval: guint) -> bool {
+        if !CONFIG.autocorrect.enabled
+            || !self.can_replace_preceding()
+            || self.word_buffer.is_empty()
+        {
+            return false;
+        }
+
+        let typed = self.word_buffer.clone();
+        let locale = self.locale();
+        match autocorrect_typed(&typed, |word| PREDICTOR.autocorrection(&locale, word)) {
+            Some((word, corrected)) => {
+                log::info!("Autocorrecting {} to {}", word, corrected);
+                let replacement = format!("{}{}", corrected, (keyval as u8) as char);
+                self.convert_typed(typed.chars().count(), keyval, &replacement);
+                if let Some(conversion) = self.last_conversion.as_mut() {
+                    conversion.autocorrected = Some(word.to_string());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Convert the emoticon just typed, now that it's been ended by `keyval`
+    unsafe fn emoticon_convert(&mut self, keyval: guint) -> bool {
+        let next = (keyval as u8) as char;
+        if !CONFIG.emoticons.enabled
+            || !self.can_replace_preceding()
+            || PREDICTOR.is_emoticon_prefix(&format!("{}{}", self.word_buffer, next))
+        {
+            return false;
+        }
+
+        match PREDICTOR.emoticon(&self.word_buffer, self.open_parentheses) {
+            Some(emoji) => {
+                let replacement = format!("{}{}", emoji, next);
+                self.convert_typed(self.word_buffer.chars().count(), keyval, &replacement);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Expand the snippet abbreviation just typed, now that it's been ended by a space
+    unsafe fn snippet_convert(&mut self, keyval: guint) -> bool {
+        let template = match PREDICTOR.snippet(&self.word_buffer) {
+            Some(template) if self.can_replace_preceding() => template,
+            _ => return false,
+        };
+
+        let expansion = snippet::expand(template);
+        let abbreviation_length = self.word_buffer.chars().count();
+        match expansion.cursor_offset {
+            None => self.convert_typed(abbreviation_length, keyval, &expansion.text),
+            Some(offset) => {
+                //once the cursor has moved the expansion can't be reverted
+                self.replace_preceding(abbreviation_length, &expansion.text);
+                self.move_cursor_back(offset);
+            }
+        }
+        true
+    }
+
+    /// Move the cursor back by forwarding left arrow presses to the application
+    unsafe fn move_cursor_back(&mut self, count: usize) {
+        for _ in 0..count {
+            ibus_engine_forward_key_event(self.parent_engine_as_ibus_engine(), IBUS_Left, 0, 0);
+            ibus_engine_forward_key_event(
+                self.parent_engine_as_ibus_engine(),
+                IBUS_Left,
+                0,
+                IBusModifierType_IBUS_RELEASE_MASK,
+            );
+        }
+    }
+
+    /// Typing in normal mode, with any enabled typographic replacements
+    unsafe fn normal_input(&mut self, keyval: guint) {
+        if ((keyval as u8) as char).is_ascii_punctuation() && self.emoticon_convert(keyval) {
+            return;
+        }
+
+        let smart_punctuation = &CONFIG.smart_punctuation;
+        if smart_punctuation.enabled && self.can_replace_preceding() {
+            if let Some(replacement) = typography::smart_punctuation(
+                smart_punctuation,
+                &self.word_buffer,
+                (keyval as u8) as char,
+                |text| CONFIG.emoticons.enabled && PREDICTOR.is_emoticon_prefix(text),
+            ) {
+                self.convert_typed(replacement.delete, keyval, replacement.text);
+                return;
+            }
+        }
+        self.commit_char(keyval);
     }
 
     unsafe fn commit_text(&mut self, text: *mut IBusText) {
@@ -162,8 +487,39 @@ impl EngineCore {
         ibus_engine_commit_text(self.parent_engine_as_ibus_engine(), text);
     }
 
+    unsafe fn commit_string(&mut self, text: String) {
+        match into_ibus_string(text) {
+            Ok(ibus_text) => self.commit_text(ibus_text),
+            Err(err) => log::error!("Failed string conversion for commit: {}", err),
+        }
+    }
+
+    unsafe fn show_underlined_preedit(&mut self, text: String) {
+        match into_ibus_string(text) {
+            Ok(preedit) => {
+                let len = ibus_text_get_length(preedit);
+                ibus_text_append_attribute(
+                    preedit,
+                    IBusAttrType_IBUS_ATTR_TYPE_UNDERLINE,
+                    IBusAttrUnderline_IBUS_ATTR_UNDERLINE_SINGLE,
+                    0,
+                    len as gint,
+                );
+                ibus_engine_update_preedit_text(
+                    self.parent_engine_as_ibus_engine(),
+                    preedit,
+                    len,
+                    GBOOL_TRUE,
+                );
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for preedit: {}", err);
+            }
+        }
+    }
+
     unsafe fn update_lookup_table(&mut self) {
-        if self.input_mode == SymbolTable {
+        if self.input_mode == SymbolTable || self.input_mode == MathCommand {
             let page_size = (*self.get_table()).page_size;
             let idx = ibus_lookup_table_get_cursor_pos(self.get_table());
             let page_num = idx / page_size;
@@ -211,7 +567,7 @@ impl EngineCore {
         }
 
         match self.input_mode {
-            SymbolTable => {
+            SymbolTable | MathCommand => {
                 let page_size = (*self.get_table()).page_size;
                 let idx = ibus_lookup_table_get_cursor_pos(self.get_table()) % page_size;
                 let symbol = ibus_lookup_table_get_label(self.get_table(), idx);
@@ -230,6 +586,9 @@ impl EngineCore {
                     GBOOL_TRUE,
                 );
             }
+            WordTable if self.word_search == Correction => {
+                ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
+            }
             WordTable => {
                 let idx = ibus_lookup_table_get_cursor_pos(self.get_table());
                 let candidate = ibus_lookup_table_get_candidate(self.get_table(), idx);
@@ -250,7 +609,7 @@ impl EngineCore {
                     );
                 };
             }
-            Normal => {}
+            Codepoint | MathScript | Normal => {}
         }
     }
 
@@ -278,11 +637,75 @@ impl EngineCore {
 
         self.input_mode = Normal;
         self.table_visible = false;
+        self.word_search = Completion;
         ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
         ibus_engine_hide_lookup_table(self.parent_engine_as_ibus_engine());
         GBOOL_TRUE
     }
 
+    /// Opens the word table with spelling corrections for the word being typed, or if there isn't
+    /// one, the word right before the cursor
+    unsafe fn correction_table_enable(&mut self) -> gboolean {
+        if self.table_visible || !self.can_replace_preceding() {
+            return GBOOL_FALSE;
+        } else if self.word_buffer.is_empty() {
+            match self.surrounding_word() {
+                Some(word) => self.word_buffer = word,
+                None => return GBOOL_FALSE,
+            }
+        }
+
+        self.word_search = Correction;
+        self.word_table_enable()
+    }
+
+    unsafe fn word_search_toggle(&mut self) -> gboolean {
+        if self.input_mode != WordTable {
+            log::error!("Call to toggle word search outside of word input mode");
+            return GBOOL_FALSE;
+        }
+
+        self.word_search = match self.word_search {
+            Completion if self.can_replace_preceding() => Correction,
+            Completion | Correction => Completion,
+        };
+        self.word_table_update();
+        GBOOL_TRUE
+    }
+
+    /// The word right before the cursor, if the client supports surrounding text and nothing is
+    /// selected
+    unsafe fn surrounding_word(&self) -> Option<String> {
+        let mut text: *mut IBusText = std::ptr::null_mut();
+        let mut cursor_pos: guint = 0;
+        let mut anchor_pos: guint = 0;
+        ibus_engine_get_surrounding_text(
+            self.parent_engine_as_ibus_engine(),
+            &mut text,
+            &mut cursor_pos,
+            &mut anchor_pos,
+        );
+        if text.is_null() || (*text).text.is_null() || anchor_pos != cursor_pos {
+            return None;
+        }
+
+        let surrounding = CStr::from_ptr((*text).text as *const c_char).to_string_lossy();
+        let before_cursor: Vec<char> = surrounding.chars().take(cursor_pos as usize).collect();
+        let word: String = before_cursor
+            .iter()
+            .rev()
+            .take_while(|c| c.is_alphabetic() || **c == '\'')
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+        if word.is_empty() {
+            None
+        } else {
+            Some(word)
+        }
+    }
+
     unsafe fn word_table_update(&mut self) {
         if !self.table_visible || self.input_mode != WordTable {
             log::error!("Word table update called while table invisible or input mode is not word");
@@ -292,7 +715,12 @@ impl EngineCore {
             return;
         }
 
-        let search_result = PREDICTOR.word(self.word_buffer.as_str());
+        let search_result = match self.word_search {
+            Completion => PREDICTOR.word(&self.locale(), self.word_buffer.as_str()),
+            Correction => {
+                PREDICTOR.corrections(&self.locale(), split_trailing(&self.word_buffer).0)
+            }
+        };
         match search_result {
             Ok(candidates) => {
                 log::info!(
@@ -332,7 +760,17 @@ impl EngineCore {
         let idx = input_idx.unwrap_or_else(|| ibus_lookup_table_get_cursor_pos(self.get_table()));
         log::info!("Word commit for idx {}", idx);
         let candidate = ibus_lookup_table_get_candidate(self.get_table(), idx);
-        if let Some(remainder) = self.get_word_remainder(candidate) {
+        if self.word_search == Correction {
+            //corrections replace the whole word, keeping any punctuation typed after it
+            match CStr::from_ptr((*candidate).text as *const c_char).to_str() {
+                Ok(word) => {
+                    let typed = self.word_buffer.clone();
+                    let trailing = split_trailing(&typed).1;
+                    self.replace_preceding(typed.chars().count(), &(word.to_string() + trailing));
+                }
+                Err(err) => log::error!("Failed to convert word to string: {}", err),
+            }
+        } else if let Some(remainder) = self.get_word_remainder(candidate) {
             self.commit_text(remainder)
         }
 
@@ -340,15 +778,75 @@ impl EngineCore {
         self.word_table_disable();
     }
 
+    /// Permanently hides the highlighted word candidate and refreshes the table
+    unsafe fn word_hide_candidate(&mut self) -> gboolean {
+        let table = self.get_table();
+        if ibus_lookup_table_get_number_of_candidates(table) == 0 {
+            return GBOOL_TRUE;
+        }
+
+        let candidate =
+            ibus_lookup_table_get_candidate(table, ibus_lookup_table_get_cursor_pos(table));
+        match CStr::from_ptr((*candidate).text as *const c_char).to_str() {
+            Ok(word) => match user_store::hide_word(word) {
+                Ok(()) => log::info!("Hid word {}", word),
+                Err(err) => log::error!("Failed to hide word {}: {}", word, err),
+            },
+            Err(err) => log::error!("Failed to convert word to string: {}", err),
+        }
+        PREDICTOR.reload_user_words();
+        self.word_table_update();
+        //don't leave an empty table open after hiding its last word
+        if self.table_visible && ibus_lookup_table_get_number_of_candidates(table) == 0 {
+            self.word_table_disable();
+        }
+        GBOOL_TRUE
+    }
+
+    /// Adds the word typed so far to the user's words and refreshes the table
+    unsafe fn word_add_typed(&mut self) -> gboolean {
+        let (word, _) = split_trailing(&self.word_buffer);
+        if word.is_empty() {
+            return GBOOL_TRUE;
+        }
+        match user_store::add_word(word) {
+            Ok(()) => log::info!("Added word {}", word),
+            Err(err) => log::error!("Failed to add word {}: {}", word, err),
+        }
+        PREDICTOR.reload_user_words();
+        self.word_table_update();
+        GBOOL_TRUE
+    }
+
     unsafe fn get_word_remainder(&self, candidate: *mut IBusText) -> Option<*mut IBusText> {
         match CStr::from_ptr((*candidate).text as *const c_char).to_str() {
-            Ok(word) => match into_ibus_string(String::from(&word[self.word_buffer.len()..])) {
-                Ok(ibus_word) => Some(ibus_word),
-                Err(err) => {
-                    log::error!("Failed to convert slice back into ibus string: {}", err);
-                    None
+            Ok(word) => {
+                //Candidates can differ from the typed text in byte length (e.g. ẞ vs ß)
+                let typed_chars = self.word_buffer.chars().count();
+                let start = word
+                    .char_indices()
+                    .map(|(index, _)| index)
+                    .chain(std::iter::once(word.len()))
+                    .nth(typed_chars);
+                let start = match start {
+                    Some(start) => start,
+                    None => {
+                        log::error!(
+                            "Candidate {} is shorter than typed {}",
+                            word,
+                            self.word_buffer
+                        );
+                        return None;
+                    }
+                };
+                match into_ibus_string(String::from(&word[start..])) {
+                    Ok(ibus_word) => Some(ibus_word),
+                    Err(err) => {
+                        log::error!("Failed to convert slice back into ibus string: {}", err);
+                        None
+                    }
                 }
-            },
+            }
             Err(err) => {
                 log::error!("Failed to convert word to string: {}", err);
                 None
@@ -368,13 +866,44 @@ impl EngineCore {
 
         self.input_mode = SymbolTable;
         self.table_visible = true;
+        self.symbol_search = Shortcode;
+        self.symbol_last_page = 0;
+        ibus_lookup_table_clear(self.get_table());
+        ibus_engine_update_lookup_table(
+            self.parent_engine_as_ibus_engine(),
+            self.get_table(),
+            GBOOL_TRUE,
+        );
+        GBOOL_TRUE
+    }
+
+    unsafe fn symbol_search_toggle(&mut self) -> gboolean {
+        if self.input_mode != SymbolTable {
+            log::error!("Call to toggle symbol search outside of symbol input mode");
+            return GBOOL_FALSE;
+        }
+
+        self.symbol_search = match self.symbol_search {
+            Shortcode if PREDICTOR.has_unicode_names() => Name,
+            Shortcode => {
+                log::warn!("Unicode name data is not installed, cannot search all characters");
+                return GBOOL_TRUE;
+            }
+            Name => {
+                //shortcodes never contain spaces
+                self.symbol_preedit.retain(|c| c != ' ');
+                Shortcode
+            }
+        };
         self.symbol_last_page = 0;
         ibus_lookup_table_clear(self.get_table());
+        self.symbol_label_vec.clear();
         ibus_engine_update_lookup_table(
             self.parent_engine_as_ibus_engine(),
             self.get_table(),
             GBOOL_TRUE,
         );
+        self.symbol_input_update();
         GBOOL_TRUE
     }
 
@@ -390,13 +919,7 @@ impl EngineCore {
         ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
         ibus_engine_hide_lookup_table(self.parent_engine_as_ibus_engine());
         ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine());
-        for i in 0..(*self.get_table()).page_size {
-            ibus_lookup_table_set_label(
-                self.get_table(),
-                i,
-                ibus_text_new_from_static_string(empty_cstring.as_ptr()),
-            );
-        }
+        self.clear_labels();
         GBOOL_TRUE
     }
 
@@ -408,7 +931,11 @@ impl EngineCore {
             return;
         }
 
-        match into_ibus_string(self.symbol_preedit.clone()) {
+        let aux_text = match self.symbol_search {
+            Shortcode => self.symbol_preedit.clone(),
+            Name => format!("[all] {}", self.symbol_preedit),
+        };
+        match into_ibus_string(aux_text) {
             Ok(ibus_string) => {
                 ibus_engine_update_auxiliary_text(
                     self.parent_engine_as_ibus_engine(),
@@ -428,7 +955,19 @@ impl EngineCore {
             return;
         }
 
-        let search_result = PREDICTOR.symbol(self.symbol_preedit.as_str());
+        let search_result = match self.symbol_search {
+            Shortcode => PREDICTOR
+                .symbol(self.symbol_preedit.as_str())
+                .map(|symbols| {
+                    symbols
+                        .into_iter()
+                        .map(|(shortcode, info)| {
+                            (symbol_candidate_text(&shortcode, &info), info.symbol)
+                        })
+                        .collect()
+                }),
+            Name => PREDICTOR.unicode_name(self.symbol_preedit.as_str()),
+        };
         match search_result {
             Ok(candidates) => {
                 log::info!(
@@ -436,49 +975,38 @@ impl EngineCore {
                     self.symbol_preedit,
                     candidates
                 );
-                let table = self.get_table();
-                // Must clear table first, since the table may have IBusText referencing the
-                // symbol_label_vec strings
-                ibus_lookup_table_clear(table);
-                self.symbol_label_vec.clear();
-                for (idx, (shortcode, ident)) in candidates.into_iter().enumerate() {
-                    match (
-                        CString::new(shortcode.into_bytes()),
-                        CString::new(ident.into_bytes()),
-                    ) {
-                        (Ok(shortcode_cstring), Ok(ident_cstring)) => {
-                            ibus_lookup_table_append_candidate(
-                                table,
-                                ibus_text_new_from_string(
-                                    shortcode_cstring.into_raw() as *mut gchar
-                                ),
-                            );
-                            self.symbol_label_vec.push(ident_cstring);
-                            if idx < (*table).page_size as usize {
-                                ibus_lookup_table_set_label(
-                                    table,
-                                    idx as guint,
-                                    ibus_text_new_from_static_string(
-                                        self.symbol_label_vec.get_unchecked(idx).as_ptr(),
-                                    ),
-                                );
-                            }
-                        }
-                        _ => {
-                            log::error!("Failed string conversion for symbol lookup");
-                        }
-                    }
+                let mut labelled_candidates: Vec<(String, String, SymbolAction)> = candidates
+                    .into_iter()
+                    .map(|(shortcode, symbol)| (shortcode, symbol, SymbolAction::Insert))
+                    .collect();
+                if self.symbol_search == Shortcode {
+                    labelled_candidates.extend(
+                        MathStyle::ALL
+                            .iter()
+                            .filter(|style| style.name().starts_with(self.symbol_preedit.as_str()))
+                            .map(|style| {
+                                (
+                                    style.name().to_string(),
+                                    style.apply('A').to_string(),
+                                    SymbolAction::Style(*style),
+                                )
+                            }),
+                    );
+                    labelled_candidates.extend(
+                        PREDICTOR
+                            .snippets(self.symbol_preedit.as_str())
+                            .into_iter()
+                            .map(|(abbreviation, text)| {
+                                let preview = snippet::preview(&text);
+                                (
+                                    abbreviation.clone(),
+                                    preview,
+                                    SymbolAction::Snippet(abbreviation),
+                                )
+                            }),
+                    );
                 }
-                log::info!(
-                    "{} candidates and {} labels",
-                    ibus_lookup_table_get_number_of_candidates(self.get_table()),
-                    self.symbol_label_vec.len()
-                );
-                ibus_engine_update_lookup_table(
-                    self.parent_engine_as_ibus_engine(),
-                    table,
-                    GBOOL_TRUE,
-                );
+                self.set_labelled_candidates(labelled_candidates);
             }
             Err(err) => {
                 log::error!("{}", err);
@@ -487,6 +1015,62 @@ impl EngineCore {
         self.update_preedit();
     }
 
+    /// Fill the table with (candidate, label, action) triples, where the label is the symbol shown
+    /// for (and usually committed by) the candidate
+    unsafe fn set_labelled_candidates(&mut self, candidates: Vec<(String, String, SymbolAction)>) {
+        let table = self.get_table();
+        // Must clear table first, since the table may have IBusText referencing the
+        // symbol_label_vec strings
+        ibus_lookup_table_clear(table);
+        self.symbol_label_vec.clear();
+        self.symbol_action_vec.clear();
+        self.symbol_last_page = 0;
+        for (shortcode, ident, action) in candidates.into_iter() {
+            match (
+                CString::new(shortcode.into_bytes()),
+                CString::new(ident.into_bytes()),
+            ) {
+                (Ok(shortcode_cstring), Ok(ident_cstring)) => {
+                    ibus_lookup_table_append_candidate(
+                        table,
+                        ibus_text_new_from_string(shortcode_cstring.into_raw() as *mut gchar),
+                    );
+                    let idx = self.symbol_label_vec.len();
+                    self.symbol_label_vec.push(ident_cstring);
+                    self.symbol_action_vec.push(action);
+                    if idx < (*table).page_size as usize {
+                        ibus_lookup_table_set_label(
+                            table,
+                            idx as guint,
+                            ibus_text_new_from_static_string(
+                                self.symbol_label_vec.get_unchecked(idx).as_ptr(),
+                            ),
+                        );
+                    }
+                }
+                _ => {
+                    log::error!("Failed string conversion for symbol lookup");
+                }
+            }
+        }
+        log::info!(
+            "{} candidates and {} labels",
+            ibus_lookup_table_get_number_of_candidates(self.get_table()),
+            self.symbol_label_vec.len()
+        );
+        ibus_engine_update_lookup_table(self.parent_engine_as_ibus_engine(), table, GBOOL_TRUE);
+    }
+
+    unsafe fn clear_labels(&mut self) {
+        for i in 0..(*self.get_table()).page_size {
+            ibus_lookup_table_set_label(
+                self.get_table(),
+                i,
+                ibus_text_new_from_static_string(empty_cstring.as_ptr()),
+            );
+        }
+    }
+
     unsafe fn symbol_commit(&mut self, input_idx: Option<guint>) {
         if self.input_mode != SymbolTable {
             log::error!("Symbol input commit called outside symbol input mode");
@@ -495,12 +1079,366 @@ impl EngineCore {
         if !self.symbol_preedit.is_empty() {
             let idx =
                 input_idx.unwrap_or_else(|| ibus_lookup_table_get_cursor_in_page(self.get_table()));
-            let symbol = ibus_lookup_table_get_label(self.get_table(), idx);
-            self.commit_text(symbol);
+            match self.get_symbol_action(idx) {
+                Some(SymbolAction::Style(style)) => {
+                    self.symbol_table_disable();
+                    self.math_style_set(Some(style));
+                    return;
+                }
+                Some(SymbolAction::Snippet(abbreviation)) => {
+                    self.symbol_table_disable();
+                    if let Some(template) = PREDICTOR.snippet(&abbreviation) {
+                        let expansion = snippet::expand(template);
+                        self.commit_string(expansion.text);
+                        self.move_cursor_back(expansion.cursor_offset.unwrap_or(0));
+                    }
+                    return;
+                }
+                Some(SymbolAction::Insert) | None => {
+                    let symbol = ibus_lookup_table_get_label(self.get_table(), idx);
+                    self.commit_text(symbol);
+                }
+            }
         }
 
         self.symbol_table_disable();
     }
+
+    /// The action for the candidate at the given index within the current page
+    unsafe fn get_symbol_action(&self, idx_in_page: guint) -> Option<SymbolAction> {
+        let page_size = (*self.get_table()).page_size;
+        let page_start = ibus_lookup_table_get_cursor_pos(self.get_table()) / page_size * page_size;
+        self.symbol_action_vec
+            .get((page_start + idx_in_page) as usize)
+            .cloned()
+    }
+
+    /*
+     ** Math input methods **
+     */
+
+    unsafe fn math_mode_toggle(&mut self) -> gboolean {
+        self.math_mode = !self.math_mode;
+        log::info!("Math mode enabled: {}", self.math_mode);
+        GBOOL_TRUE
+    }
+
+    /// Typing in normal mode while math mode is enabled
+    unsafe fn math_input(&mut self, keyval: guint) {
+        let next = (keyval as u8) as char;
+        if keyval == IBUS_backslash {
+            self.math_command_enable();
+        } else if math::is_script_marker(next) {
+            self.math_script_enable(next);
+        } else {
+            self.commit_char(keyval);
+        }
+    }
+
+    unsafe fn math_command_enable(&mut self) -> gboolean {
+        if self.table_visible {
+            log::error!("Call to enable math command input while a table is already visible");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = MathCommand;
+        self.table_visible = true;
+        self.math_preedit.push('\\');
+        self.math_command_update();
+        GBOOL_TRUE
+    }
+
+    unsafe fn math_command_disable(&mut self) -> gboolean {
+        if self.input_mode != MathCommand {
+            log::error!("Call to disable math command input outside of math command mode");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Normal;
+        self.table_visible = false;
+        self.math_preedit.clear();
+        ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
+        ibus_engine_hide_lookup_table(self.parent_engine_as_ibus_engine());
+        ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine());
+        self.clear_labels();
+        GBOOL_TRUE
+    }
+
+    /// Typing while a command is being entered. Anything that can't be part of the command is a
+    /// delimiter, which converts the command and is then typed as usual.
+    unsafe fn math_command_input(&mut self, keyval: guint) {
+        let next = (keyval as u8) as char;
+        if math::is_command_char(next) {
+            self.math_preedit.push(next);
+            self.math_command_update();
+        } else {
+            self.math_command_convert();
+            self.math_input(keyval);
+        }
+    }
+
+    unsafe fn math_command_update(&mut self) {
+        if !self.table_visible || self.input_mode != MathCommand {
+            log::error!(
+                "Math command update called while table invisible or input mode is not math"
+            );
+            return;
+        }
+
+        match into_ibus_string(self.math_preedit.clone()) {
+            Ok(ibus_string) => {
+                ibus_engine_update_auxiliary_text(
+                    self.parent_engine_as_ibus_engine(),
+                    ibus_string,
+                    GBOOL_TRUE,
+                );
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for math aux text update: {}", err);
+            }
+        }
+
+        match PREDICTOR.latex(self.math_preedit.as_str()) {
+            Ok(candidates) => {
+                log::info!(
+                    "Math command search for {} and got {:?}",
+                    self.math_preedit,
+                    candidates
+                );
+                self.set_labelled_candidates(
+                    candidates
+                        .into_iter()
+                        .map(|(command, symbol)| (command, symbol, SymbolAction::Insert))
+                        .collect(),
+                );
+            }
+            Err(err) => {
+                log::error!("{}", err);
+            }
+        }
+        self.update_preedit();
+    }
+
+    /// Replace a completed command with its symbol, or type it out as-is if it isn't known
+    unsafe fn math_command_convert(&mut self) {
+        if self.input_mode != MathCommand {
+            log::error!("Math command conversion called outside math command mode");
+            return;
+        }
+
+        let command = std::mem::take(&mut self.math_preedit);
+        let text = PREDICTOR.latex_symbol(command.as_str()).unwrap_or(command);
+        self.math_command_disable();
+        self.commit_string(text);
+    }
+
+    unsafe fn math_command_commit(&mut self, input_idx: Option<guint>) {
+        if self.input_mode != MathCommand {
+            log::error!("Math command commit called outside math command mode");
+            return;
+        }
+
+        if ibus_lookup_table_get_number_of_candidates(self.get_table()) == 0 {
+            self.math_command_convert();
+            return;
+        }
+
+        let idx =
+            input_idx.unwrap_or_else(|| ibus_lookup_table_get_cursor_in_page(self.get_table()));
+        let symbol = ibus_lookup_table_get_label(self.get_table(), idx);
+        self.commit_text(symbol);
+        self.math_command_disable();
+    }
+
+    unsafe fn math_style_set(&mut self, style: Option<MathStyle>) {
+        self.math_style = style;
+        match style {
+            Some(style) => {
+                match into_ibus_string(format!("{} {}", style.apply('A'), style.name())) {
+                    Ok(ibus_string) => {
+                        ibus_engine_update_auxiliary_text(
+                            self.parent_engine_as_ibus_engine(),
+                            ibus_string,
+                            GBOOL_TRUE,
+                        );
+                    }
+                    Err(err) => {
+                        log::error!("Failed string conversion for math style aux text: {}", err);
+                    }
+                }
+            }
+            None => ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine()),
+        }
+    }
+
+    unsafe fn math_style_cycle(&mut self) -> gboolean {
+        self.math_style_set(MathStyle::cycle(self.math_style));
+        GBOOL_TRUE
+    }
+
+    /// Letters and digits typed while a style is selected are styled, and anything else ends the
+    /// styled run and is typed as usual
+    unsafe fn math_style_input(&mut self, keyval: guint) {
+        let next = (keyval as u8) as char;
+        match self.math_style {
+            Some(style) if next.is_ascii_alphanumeric() => {
+                self.commit_string(style.apply(next).to_string());
+            }
+            _ => {
+                self.math_style_set(None);
+                if self.math_mode {
+                    self.math_input(keyval);
+                } else {
+                    self.commit_char(keyval);
+                }
+            }
+        }
+    }
+
+    unsafe fn math_script_enable(&mut self, marker: char) -> gboolean {
+        if self.table_visible {
+            log::error!("Call to enable math script input while a table is visible");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = MathScript;
+        self.math_preedit.push(marker);
+        self.math_script_update();
+        GBOOL_TRUE
+    }
+
+    unsafe fn math_script_disable(&mut self) -> gboolean {
+        if self.input_mode != MathScript {
+            log::error!("Call to disable math script input outside of math script mode");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Normal;
+        self.math_preedit.clear();
+        ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
+        GBOOL_TRUE
+    }
+
+    /// Typing after ^ or _, which is converted as soon as the superscript/subscript is complete
+    unsafe fn math_script_input(&mut self, keyval: guint) {
+        let next = (keyval as u8) as char;
+        if next == ' ' && self.math_preedit.len() == 1 {
+            //nothing to convert, so the marker is just typed as-is
+            self.math_script_convert();
+            self.commit_char(keyval);
+            return;
+        }
+
+        self.math_preedit.push(next);
+        if math::is_script_complete(&self.math_preedit) {
+            self.math_script_convert();
+        } else {
+            self.math_script_update();
+        }
+    }
+
+    unsafe fn math_script_update(&mut self) {
+        if self.input_mode != MathScript {
+            log::error!("Math script update called while input mode is not math script");
+            return;
+        }
+
+        self.show_underlined_preedit(self.math_preedit.clone());
+    }
+
+    unsafe fn math_script_convert(&mut self) {
+        if self.input_mode != MathScript {
+            log::error!("Math script conversion called outside math script mode");
+            return;
+        }
+
+        let text = math::convert_script(&self.math_preedit);
+        self.math_script_disable();
+        self.commit_string(text);
+    }
+
+    /*
+     ** Codepoint input methods **
+     */
+
+    unsafe fn codepoint_enable(&mut self) -> gboolean {
+        if self.table_visible {
+            log::error!("Call to enable codepoint input while a table is visible");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Codepoint;
+        self.codepoint_update();
+        GBOOL_TRUE
+    }
+
+    unsafe fn codepoint_disable(&mut self) -> gboolean {
+        if self.input_mode != Codepoint {
+            log::error!("Call to disable codepoint input outside of codepoint input mode");
+            return GBOOL_FALSE;
+        }
+
+        self.input_mode = Normal;
+        self.codepoint_preedit.clear();
+        ibus_engine_hide_preedit_text(self.parent_engine_as_ibus_engine());
+        ibus_engine_hide_auxiliary_text(self.parent_engine_as_ibus_engine());
+        GBOOL_TRUE
+    }
+
+    unsafe fn codepoint_push(&mut self, next: char) {
+        if codepoint::accepts(&self.codepoint_preedit, next) {
+            self.codepoint_preedit.push(next);
+            self.codepoint_update();
+        } else {
+            log::info!(
+                "Rejected {} for codepoint input {}",
+                next,
+                self.codepoint_preedit
+            );
+        }
+    }
+
+    unsafe fn codepoint_update(&mut self) {
+        if self.input_mode != Codepoint {
+            log::error!("Codepoint update called while input mode is not codepoint");
+            return;
+        }
+
+        match into_ibus_string(codepoint::describe(&self.codepoint_preedit)) {
+            Ok(ibus_string) => {
+                ibus_engine_update_auxiliary_text(
+                    self.parent_engine_as_ibus_engine(),
+                    ibus_string,
+                    GBOOL_TRUE,
+                );
+            }
+            Err(err) => {
+                log::error!("Failed string conversion for codepoint aux text: {}", err);
+            }
+        }
+
+        self.show_underlined_preedit(codepoint::preview(&self.codepoint_preedit));
+    }
+
+    unsafe fn codepoint_commit(&mut self) {
+        if self.input_mode != Codepoint {
+            log::error!("Codepoint commit called outside codepoint input mode");
+            return;
+        }
+
+        match parse_codepoints(&self.codepoint_preedit) {
+            Ok(text) => {
+                if !text.is_empty() {
+                    self.commit_string(text);
+                }
+                self.codepoint_disable();
+            }
+            Err(err) => {
+                //stay in codepoint mode so the input can be corrected
+                log::error!("Could not commit {}: {}", self.codepoint_preedit, err);
+            }
+        }
+    }
 }
 
 #[no_mangle]
@@ -544,11 +1482,42 @@ pub unsafe extern "C" fn ibus_eei_engine_page_up_button(engine: *mut IBusEngine)
         }
     }
 }
+/// Warns when the engine's dictionary isn't installed, since its words would otherwise quietly be
+/// predicted from the built-in one
+#[no_mangle]
+pub unsafe extern "C" fn ibus_eei_engine_check_dictionary(engine: *mut IBusEngine) {
+    match EngineCore::get(engine) {
+        Some(engine_core) => {
+            let locale = engine_core.locale();
+            if PREDICTOR.has_dictionary(&locale) {
+                return;
+            }
+            log::warn!(
+                "No {} dictionary installed, using {}",
+                locale,
+                DEFAULT_LOCALE
+            );
+            let message = format!(
+                "No {} dictionary installed, predicting {} words",
+                locale, DEFAULT_LOCALE
+            );
+            match into_ibus_string(message) {
+                Ok(text) => ibus_engine_update_auxiliary_text(engine, text, GBOOL_TRUE),
+                Err(err) => log::error!("Failed string conversion for dictionary warning: {}", err),
+            }
+        }
+        None => {
+            log::error!("Could not retrieve engine core for dictionary check");
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ibus_eei_engine_focus_out(engine: *mut IBusEngine) {
     match EngineCore::get(engine) {
         Some(engine_core) => {
             engine_core.abort_table_input();
+            engine_core.clear_typed();
             match (*engine_core.parent_engine_class).focus_out {
                 Some(parent_focus_out) => {
                     parent_focus_out(engine);
@@ -569,6 +1538,7 @@ pub unsafe extern "C" fn ibus_eei_engine_reset(engine: *mut IBusEngine) {
     match EngineCore::get(engine) {
         Some(engine_core) => {
             engine_core.abort_table_input();
+            engine_core.clear_typed();
             match (*engine_core.parent_engine_class).reset {
                 Some(parent_reset) => {
                     parent_reset(engine);
@@ -627,19 +1597,52 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(
         }
     };
 
+    //conversions can only be reverted by the key press immediately after them
+    let last_conversion = if modifiers & IBusModifierType_IBUS_RELEASE_MASK == 0 {
+        engine_core.last_conversion.take()
+    } else {
+        None
+    };
+
     if modifiers == IBusModifierType_IBUS_CONTROL_MASK {
         //control key (and only control key) is held down
         return match keyval {
             IBUS_e => match engine_core.input_mode {
                 SymbolTable => engine_core.symbol_table_disable(),
-                WordTable => GBOOL_FALSE,
+                WordTable | Codepoint | MathCommand | MathScript => GBOOL_FALSE,
                 Normal => engine_core.symbol_table_enable(),
             },
             IBUS_w => match engine_core.input_mode {
-                SymbolTable => GBOOL_FALSE,
+                SymbolTable | Codepoint | MathCommand | MathScript => GBOOL_FALSE,
                 WordTable => engine_core.word_table_disable(),
                 Normal => engine_core.word_table_enable(),
             },
+            IBUS_d => match engine_core.input_mode {
+                SymbolTable | Codepoint | MathCommand | MathScript | Normal => GBOOL_FALSE,
+                WordTable => engine_core.word_hide_candidate(),
+            },
+            IBUS_a => match engine_core.input_mode {
+                SymbolTable | Codepoint | MathCommand | MathScript | Normal => GBOOL_FALSE,
+                WordTable => engine_core.word_add_typed(),
+            },
+            IBUS_r => match engine_core.input_mode {
+                SymbolTable | Codepoint | MathCommand | MathScript => GBOOL_FALSE,
+                WordTable => engine_core.word_search_toggle(),
+                Normal => engine_core.correction_table_enable(),
+            },
+            IBUS_u => match engine_core.input_mode {
+                SymbolTable | WordTable | MathCommand | MathScript => GBOOL_FALSE,
+                Codepoint => engine_core.codepoint_disable(),
+                Normal => engine_core.codepoint_enable(),
+            },
+            IBUS_b => match engine_core.input_mode {
+                SymbolTable | WordTable | Codepoint | MathCommand | MathScript => GBOOL_FALSE,
+                Normal => engine_core.math_style_cycle(),
+            },
+            IBUS_m => match engine_core.input_mode {
+                SymbolTable | WordTable | Codepoint | MathCommand | MathScript => GBOOL_FALSE,
+                Normal => engine_core.math_mode_toggle(),
+            },
             _ => GBOOL_FALSE,
         };
     } else if (modifiers & !IBusModifierType_IBUS_SHIFT_MASK) != 0 {
@@ -649,21 +1652,55 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(
     match keyval {
         IBUS_space => {
             match engine_core.input_mode {
+                SymbolTable if engine_core.symbol_search == Name => {
+                    //character names are searched word by word
+                    if !engine_core.symbol_preedit.is_empty() {
+                        engine_core.symbol_preedit.push(' ');
+                        engine_core.symbol_input_update();
+                    }
+                    return GBOOL_TRUE;
+                }
                 SymbolTable => {
                     engine_core.symbol_table_disable();
                 }
                 WordTable => {
                     engine_core.word_table_disable();
                 }
-                Normal => {}
+                Codepoint => {
+                    //spaces separate codepoints
+                    engine_core.codepoint_push(' ');
+                    return GBOOL_TRUE;
+                }
+                MathCommand => {
+                    //like in LaTeX, a space only ends the command
+                    engine_core.math_command_convert();
+                    return GBOOL_TRUE;
+                }
+                MathScript => {
+                    engine_core.math_script_input(keyval);
+                    return GBOOL_TRUE;
+                }
+                Normal => {
+                    if engine_core.math_style.is_some() {
+                        engine_core.math_style_set(None);
+                    } else if engine_core.snippet_convert(keyval)
+                        || engine_core.emoticon_convert(keyval)
+                        || engine_core.autocorrect_convert(keyval)
+                    {
+                        engine_core.word_buffer.clear();
+                        return GBOOL_TRUE;
+                    }
+                }
             }
             engine_core.commit_char(keyval);
-            engine_core.word_buffer.clear();
+            engine_core.end_word();
             GBOOL_TRUE
         }
         IBUS_Return => engine_core.commit_from_table(None),
         IBUS_Right | IBUS_Left => {
-            if engine_core.input_mode == WordTable {
+            if matches!(engine_core.input_mode, Codepoint | MathCommand | MathScript) {
+                return GBOOL_TRUE;
+            } else if engine_core.input_mode == WordTable {
                 engine_core.word_table_disable();
             }
             engine_core.word_buffer.clear();
@@ -703,16 +1740,56 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(
                     engine_core.word_table_update();
                     GBOOL_FALSE //let the character pass through so deletion still happens
                 }
-                Normal => {
-                    engine_core.word_buffer.pop();
-                    GBOOL_FALSE
+                Codepoint => {
+                    engine_core.codepoint_preedit.pop();
+                    if engine_core.codepoint_preedit.is_empty() {
+                        engine_core.codepoint_disable();
+                    } else {
+                        engine_core.codepoint_update();
+                    }
+                    GBOOL_TRUE
                 }
+                MathCommand => {
+                    engine_core.math_preedit.pop();
+                    if engine_core.math_preedit.is_empty() {
+                        engine_core.math_command_disable();
+                    } else {
+                        engine_core.math_command_update();
+                    }
+                    GBOOL_TRUE
+                }
+                MathScript => {
+                    engine_core.math_preedit.pop();
+                    if engine_core.math_preedit.is_empty() {
+                        engine_core.math_script_disable();
+                    } else {
+                        engine_core.math_script_update();
+                    }
+                    GBOOL_TRUE
+                }
+                Normal => match last_conversion {
+                    Some(conversion) => {
+                        engine_core.revert_conversion(conversion);
+                        GBOOL_TRUE
+                    }
+                    None => {
+                        engine_core.word_buffer.pop();
+                        GBOOL_FALSE
+                    }
+                },
+            }
+        }
+        IBUS_Tab => {
+            if engine_core.input_mode == SymbolTable {
+                engine_core.symbol_search_toggle()
+            } else {
+                GBOOL_FALSE
             }
         }
         IBUS_Page_Down => engine_core.page_down_and_update(),
         IBUS_Page_Up => engine_core.page_up_and_update(),
         IBUS_Escape => engine_core.abort_table_input(),
-        IBUS_space..=IBUS_asciitilde => {
+        IBUS_exclam..=IBUS_asciitilde => {
             match engine_core.input_mode {
                 SymbolTable => {
                     engine_core.symbol_preedit.push((keyval as u8) as char);
@@ -722,32 +1799,48 @@ pub unsafe extern "C" fn ibus_eei_engine_process_key_event(
                     engine_core.commit_char(keyval);
                     engine_core.word_table_update();
                 }
+                Codepoint => {
+                    engine_core.codepoint_push((keyval as u8) as char);
+                }
+                MathCommand => {
+                    engine_core.math_command_input(keyval);
+                }
+                MathScript => {
+                    engine_core.math_script_input(keyval);
+                }
+                Normal if engine_core.math_style.is_some() => {
+                    engine_core.math_style_input(keyval);
+                }
+                Normal if engine_core.math_mode => {
+                    engine_core.math_input(keyval);
+                }
                 Normal => {
-                    engine_core.commit_char(keyval);
+                    engine_core.normal_input(keyval);
                 }
             }
             GBOOL_TRUE
         }
+        //letters outside ASCII, like ä and ß on a German layout, are part of words too
+        _ if matches!(engine_core.input_mode, Normal | WordTable)
+            && engine_core.math_style.is_none()
+            && !engine_core.math_mode
+            && char::from_u32(ibus_keyval_to_unicode(keyval)).is_some_and(char::is_alphabetic) =>
+        {
+            engine_core.commit_char(keyval);
+            if engine_core.input_mode == WordTable {
+                engine_core.word_table_update();
+            }
+            GBOOL_TRUE
+        }
         _ => GBOOL_FALSE,
     }
 }
 
-static DATA_DIRNAME: &str = "eei";
-
 #[no_mangle]
 pub unsafe extern "C" fn configure_logging() {
     //https://stackoverflow.com/questions/56345288/how-do-i-use-log4rs-rollingfileappender-to-incorporate-rolling-logging
 
-    let log_location = std::env::var("XDG_DATA_HOME")
-        .map(|dir| Path::new(dir.as_str()).join(DATA_DIRNAME))
-        .or(std::env::var("HOME").map(|home| {
-            Path::new(home.as_str())
-                .join(".local")
-                .join("share")
-                .join(DATA_DIRNAME)
-        }));
-
-    match log_location {
+    match paths::data_dir() {
         Ok(location) => {
             // https://stackoverflow.com/questions/56345288/how-do-i-use-log4rs-rollingfileappender-to-incorporate-rolling-logging
             let window_size = 3; // log0, log1, log2
@@ -800,3 +1893,26 @@ pub unsafe extern "C" fn configure_logging() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::autocorrect_typed;
+
+    #[test]
+    fn autocorrect_trailing_punctuation() {
+        let autocorrect = |typed| {
+            autocorrect_typed(typed, |word| {
+                if word == "teh" {
+                    Some("the".to_string())
+                } else {
+                    None
+                }
+            })
+        };
+        assert_eq!(autocorrect("teh"), Some(("teh", "the".to_string())));
+        assert_eq!(autocorrect("teh,"), Some(("teh", "the,".to_string())));
+        assert_eq!(autocorrect("teh.\""), Some(("teh", "the.\"".to_string())));
+        assert_eq!(autocorrect("the,"), None);
+        assert_eq!(autocorrect("..."), None);
+    }
+}
@@ -0,0 +1,317 @@
+/// Characters that continue a LaTeX command, including the braces of commands like `\mathbb{R}`
+pub fn is_command_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '{' || c == '}'
+}
+
+pub fn superscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'a' => 'ᵃ',
+        'b' => 'ᵇ',
+        'c' => 'ᶜ',
+        'd' => 'ᵈ',
+        'e' => 'ᵉ',
+        'f' => 'ᶠ',
+        'g' => 'ᵍ',
+        'h' => 'ʰ',
+        'i' => 'ⁱ',
+        'j' => 'ʲ',
+        'k' => 'ᵏ',
+        'l' => 'ˡ',
+        'm' => 'ᵐ',
+        'n' => 'ⁿ',
+        'o' => 'ᵒ',
+        'p' => 'ᵖ',
+        'r' => 'ʳ',
+        's' => 'ˢ',
+        't' => 'ᵗ',
+        'u' => 'ᵘ',
+        'v' => 'ᵛ',
+        'w' => 'ʷ',
+        'x' => 'ˣ',
+        'y' => 'ʸ',
+        'z' => 'ᶻ',
+        'A' => 'ᴬ',
+        'B' => 'ᴮ',
+        'D' => 'ᴰ',
+        'E' => 'ᴱ',
+        'G' => 'ᴳ',
+        'H' => 'ᴴ',
+        'I' => 'ᴵ',
+        'J' => 'ᴶ',
+        'K' => 'ᴷ',
+        'L' => 'ᴸ',
+        'M' => 'ᴹ',
+        'N' => 'ᴺ',
+        'O' => 'ᴼ',
+        'P' => 'ᴾ',
+        'R' => 'ᴿ',
+        'T' => 'ᵀ',
+        'U' => 'ᵁ',
+        'V' => 'ⱽ',
+        'W' => 'ᵂ',
+        ' ' => ' ',
+        _ => return None,
+    })
+}
+
+pub fn subscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'h' => 'ₕ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'l' => 'ₗ',
+        'm' => 'ₘ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'p' => 'ₚ',
+        'r' => 'ᵣ',
+        's' => 'ₛ',
+        't' => 'ₜ',
+        'u' => 'ᵤ',
+        'v' => 'ᵥ',
+        'x' => 'ₓ',
+        ' ' => ' ',
+        _ => return None,
+    })
+}
+
+pub fn is_script_marker(c: char) -> bool {
+    c == '^' || c == '_'
+}
+
+/// The part of a `^...`/`_...` sequence to be converted, and whether the sequence is complete.
+/// `^2` and `^(n+1)` keep everything after the marker, while the braces in `_{10}` only group.
+fn script_body(preedit: &str) -> (&str, bool) {
+    let body = preedit.get(1..).unwrap_or("");
+    let (open, close) = match body.chars().next() {
+        Some('{') => ('{', '}'),
+        Some('(') => ('(', ')'),
+        Some(_) => return (body, true),
+        None => return (body, false),
+    };
+
+    let mut depth = 0;
+    for (idx, c) in body.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                let group = &body[..idx + 1];
+                return if open == '{' {
+                    (&group[1..group.len() - 1], true)
+                } else {
+                    (group, true)
+                };
+            }
+        }
+    }
+
+    let unclosed = if open == '{' { &body[1..] } else { body };
+    (unclosed, false)
+}
+
+pub fn is_script_complete(preedit: &str) -> bool {
+    script_body(preedit).1
+}
+
+/// Convert a `^...`/`_...` sequence to superscript or subscript characters. If any character has
+/// no superscript/subscript form the sequence is left as it was typed.
+pub fn convert_script(preedit: &str) -> String {
+    let convert_char = if preedit.starts_with('^') {
+        superscript
+    } else {
+        subscript
+    };
+
+    let (body, _complete) = script_body(preedit);
+    if body.trim().is_empty() {
+        return preedit.to_string();
+    }
+
+    body.chars()
+        .map(convert_char)
+        .collect::<Option<String>>()
+        .unwrap_or_else(|| preedit.to_string())
+}
+
+/// Styles from the Mathematical Alphanumeric Symbols block
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MathStyle {
+    DoubleStruck,
+    Bold,
+    Italic,
+    Script,
+    Fraktur,
+    SansSerif,
+    Monospace,
+}
+
+impl MathStyle {
+    pub const ALL: [MathStyle; 7] = [
+        MathStyle::DoubleStruck,
+        MathStyle::Bold,
+        MathStyle::Italic,
+        MathStyle::Script,
+        MathStyle::Fraktur,
+        MathStyle::SansSerif,
+        MathStyle::Monospace,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MathStyle::DoubleStruck => "double_struck",
+            MathStyle::Bold => "bold",
+            MathStyle::Italic => "italic",
+            MathStyle::Script => "script",
+            MathStyle::Fraktur => "fraktur",
+            MathStyle::SansSerif => "sans_serif",
+            MathStyle::Monospace => "monospace",
+        }
+    }
+
+    /// The style after this one when cycling through styles, where None is unstyled
+    pub fn cycle(style: Option<MathStyle>) -> Option<MathStyle> {
+        match style {
+            None => Some(MathStyle::ALL[0]),
+            Some(style) => MathStyle::ALL
+                .iter()
+                .position(|s| *s == style)
+                .and_then(|idx| MathStyle::ALL.get(idx + 1))
+                .copied(),
+        }
+    }
+
+    /// Codepoints of the styled A, a and 0 (if the style has digits)
+    fn block_starts(&self) -> (u32, u32, Option<u32>) {
+        match self {
+            MathStyle::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE)),
+            MathStyle::Italic => (0x1D434, 0x1D44E, None),
+            MathStyle::Script => (0x1D49C, 0x1D4B6, None),
+            MathStyle::Fraktur => (0x1D504, 0x1D51E, None),
+            MathStyle::DoubleStruck => (0x1D538, 0x1D552, Some(0x1D7D8)),
+            MathStyle::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2)),
+            MathStyle::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6)),
+        }
+    }
+
+    /// Letters that were already encoded in Letterlike Symbols, and are left as reserved holes in
+    /// the Mathematical Alphanumeric Symbols block
+    fn legacy_exception(&self, c: char) -> Option<char> {
+        Some(match (self, c) {
+            (MathStyle::Italic, 'h') => 'ℎ',
+            (MathStyle::Script, 'B') => 'ℬ',
+            (MathStyle::Script, 'E') => 'ℰ',
+            (MathStyle::Script, 'F') => 'ℱ',
+            (MathStyle::Script, 'H') => 'ℋ',
+            (MathStyle::Script, 'I') => 'ℐ',
+            (MathStyle::Script, 'L') => 'ℒ',
+            (MathStyle::Script, 'M') => 'ℳ',
+            (MathStyle::Script, 'R') => 'ℛ',
+            (MathStyle::Script, 'e') => 'ℯ',
+            (MathStyle::Script, 'g') => 'ℊ',
+            (MathStyle::Script, 'o') => 'ℴ',
+            (MathStyle::Fraktur, 'C') => 'ℭ',
+            (MathStyle::Fraktur, 'H') => 'ℌ',
+            (MathStyle::Fraktur, 'I') => 'ℑ',
+            (MathStyle::Fraktur, 'R') => 'ℜ',
+            (MathStyle::Fraktur, 'Z') => 'ℨ',
+            (MathStyle::DoubleStruck, 'C') => 'ℂ',
+            (MathStyle::DoubleStruck, 'H') => 'ℍ',
+            (MathStyle::DoubleStruck, 'N') => 'ℕ',
+            (MathStyle::DoubleStruck, 'P') => 'ℙ',
+            (MathStyle::DoubleStruck, 'Q') => 'ℚ',
+            (MathStyle::DoubleStruck, 'R') => 'ℝ',
+            (MathStyle::DoubleStruck, 'Z') => 'ℤ',
+            _ => return None,
+        })
+    }
+
+    /// Map an ASCII letter or digit into this style, leaving anything unmappable unchanged
+    pub fn apply(&self, c: char) -> char {
+        if let Some(exception) = self.legacy_exception(c) {
+            return exception;
+        }
+
+        let (capital_start, small_start, digit_start) = self.block_starts();
+        let codepoint = match c {
+            'A'..='Z' => Some(capital_start + (c as u32 - 'A' as u32)),
+            'a'..='z' => Some(small_start + (c as u32 - 'a' as u32)),
+            '0'..='9' => digit_start.map(|start| start + (c as u32 - '0' as u32)),
+            _ => None,
+        };
+        codepoint.and_then(char::from_u32).unwrap_or(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{convert_script, is_script_complete, MathStyle};
+
+    #[test]
+    fn styles() {
+        assert_eq!(MathStyle::DoubleStruck.apply('R'), 'ℝ');
+        assert_eq!(MathStyle::DoubleStruck.apply('A'), '𝔸');
+        assert_eq!(MathStyle::DoubleStruck.apply('1'), '𝟙');
+        assert_eq!(MathStyle::Fraktur.apply('F'), '𝔉');
+        assert_eq!(MathStyle::Script.apply('A'), '𝒜');
+        assert_eq!(MathStyle::Bold.apply('v'), '𝐯');
+        assert_eq!(MathStyle::Italic.apply('h'), 'ℎ');
+        assert_eq!(MathStyle::Italic.apply('2'), '2');
+        assert_eq!(MathStyle::Monospace.apply('+'), '+');
+        assert_eq!(MathStyle::cycle(None), Some(MathStyle::DoubleStruck));
+        assert_eq!(MathStyle::cycle(Some(MathStyle::Monospace)), None);
+    }
+
+    #[test]
+    fn scripts() {
+        assert!(!is_script_complete("^"));
+        assert!(is_script_complete("^2"));
+        assert!(!is_script_complete("^(n+1"));
+        assert!(is_script_complete("^(n+1)"));
+        assert!(!is_script_complete("_{10"));
+        assert!(is_script_complete("_{10}"));
+
+        assert_eq!(convert_script("^2"), "²");
+        assert_eq!(convert_script("^(n+1)"), "⁽ⁿ⁺¹⁾");
+        assert_eq!(convert_script("_i"), "ᵢ");
+        assert_eq!(convert_script("_{10}"), "₁₀");
+        assert_eq!(convert_script("_{10"), "₁₀");
+        assert_eq!(convert_script("^q"), "^q");
+        assert_eq!(convert_script("_{bq}"), "_{bq}");
+        assert_eq!(convert_script("^"), "^");
+    }
+}
@@ -0,0 +1,28 @@
+use std::env::VarError;
+use std::path::{Path, PathBuf};
+
+static EEI_DIRNAME: &str = "eei";
+
+pub fn home_dir() -> Result<PathBuf, VarError> {
+    std::env::var("HOME").map(PathBuf::from)
+}
+
+/// `$XDG_DATA_HOME/eei`, falling back to `~/.local/share/eei`
+pub fn data_dir() -> Result<PathBuf, VarError> {
+    std::env::var("XDG_DATA_HOME")
+        .map(|dir| Path::new(dir.as_str()).join(EEI_DIRNAME))
+        .or(std::env::var("HOME").map(|home| {
+            Path::new(home.as_str())
+                .join(".local")
+                .join("share")
+                .join(EEI_DIRNAME)
+        }))
+}
+
+/// `$XDG_CONFIG_HOME/eei`, falling back to `~/.config/eei`
+pub fn config_dir() -> Result<PathBuf, VarError> {
+    std::env::var("XDG_CONFIG_HOME")
+        .map(|dir| Path::new(dir.as_str()).join(EEI_DIRNAME))
+        .or(std::env::var("HOME")
+            .map(|home| Path::new(home.as_str()).join(".config").join(EEI_DIRNAME)))
+}
@@ -1,64 +1,607 @@
+use crate::blocklist::Blocklist;
+use crate::config::CONFIG;
+use crate::paths::{config_dir, data_dir, home_dir};
 use crate::predict::PredictionError::*;
-use fst::automaton::{Automaton, Str};
-use fst::{IntoStreamer, Map};
+use crate::spelling::typo_cost;
+use crate::user_store::{
+    parse_word_set, AUTOCORRECT_EXCEPTIONS_FILE, HIDDEN_WORDS_FILE, USER_WORDS_FILE,
+};
+use crate::watched::WatchedFile;
+use common::bundle::{
+    dictionary_bundle, Bundle, BundleError, COMMANDS_SECTION, DEFAULT_LOCALE, DICTIONARY_SECTION,
+    LATEX_COMMANDS_BUNDLE, NAMES_SECTION, SHORTCODES_SECTION, SYMBOLS_BUNDLE, SYMBOLS_SECTION,
+    UNICODE_NAMES_BUNDLE,
+};
+use common::symbols::{
+    key_shortcode, parse_shortcode_entries, ShortcodeEntry, SymbolData, SymbolInfo,
+};
+use common::unicode::{is_name_word_separator, NAME_SEPARATOR};
+use common::words::{parse_word_counts, DEFAULT_WORD_COUNT};
+use fst::automaton::{Automaton, Levenshtein, Str};
+use fst::map::OpBuilder;
+use fst::{IntoStreamer, Map, Streamer};
 use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::sync::{Arc, Mutex};
 
 pub struct Predictor {
-    dictionary: Map<Vec<u8>>,
+    /// Word dictionaries by locale, always including the built-in `DEFAULT_LOCALE` one
+    dictionaries: BTreeMap<String, Arc<Map<Vec<u8>>>>,
+    /// Domain word packs by name, layered on top of the locale's dictionary
+    packs: BTreeMap<String, Arc<Map<Vec<u8>>>>,
+    /// Common phrases by locale, completed alongside single words
+    phrases: BTreeMap<String, Arc<Map<Vec<u8>>>>,
+    user_words: WatchedDictionary,
+    /// hunspell and aspell personal dictionaries by locale, found the first time a locale is used
+    personal_dictionaries: Mutex<HashMap<String, Vec<WatchedDictionary>>>,
+    /// Words the user has hidden, which are never suggested
+    hidden_words: WatchedFile<HashSet<String>>,
+    misspellings: HashMap<String, String>,
+    /// Words the user has reverted autocorrections of
+    autocorrect_exceptions: WatchedFile<HashSet<String>>,
+    /// The shipped blocklist of offensive words, and the user's own in `blocklist.txt`
+    blocklist: Blocklist,
+    user_blocklist: WatchedFile<Blocklist>,
     shortcode_dictionary: Map<Vec<u8>>,
-    symbols: Vec<String>,
+    symbols: SymbolData,
+    unicode_names: Option<Map<Vec<u8>>>,
+    latex_commands: Option<Map<Vec<u8>>>,
+    emoticons: BTreeMap<String, String>,
+    snippets: BTreeMap<String, String>,
+    user_shortcodes: WatchedFile<BTreeMap<String, SymbolInfo>>,
 }
 
 #[derive(Debug)]
 pub enum PredictionError {
     FstError(fst::Error),
+    LevenshteinError(fst::automaton::LevenshteinError),
     MissingSymbol(String, u64),
+    MissingData(&'static str),
+    InvalidBundle(String, BundleError),
+    InvalidSymbols(String),
 }
 
 impl fmt::Display for PredictionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             FstError(err) => write!(f, "FST error: {}", err),
+            LevenshteinError(err) => write!(f, "Spelling correction error: {}", err),
             MissingSymbol(sym, codepoint) => {
                 write!(f, "Missing shortcode: {}, for codepoint {}", sym, codepoint)
             }
+            MissingData(filename) => write!(f, "Missing data file: {}", filename),
+            InvalidBundle(filename, err) => write!(f, "Invalid data in {}: {}", filename, err),
+            InvalidSymbols(err) => write!(f, "Invalid symbol data: {}", err),
         }
     }
 }
 
+static EMOTICONS_FILE: &str = "emoticons.tsv";
+static SNIPPETS_FILE: &str = "snippets.toml";
+static USER_SHORTCODES_FILE: &str = "shortcodes.tsv";
+static USER_BLOCKLIST_FILE: &str = "blocklist.txt";
+static DICTIONARY_KIND: &str = "dictionary";
+static PACK_KIND: &str = "pack";
+static PHRASES_KIND: &str = "phrases";
+
+/// A user word list, rebuilt into a dictionary whenever it changes
+type WatchedDictionary = WatchedFile<Map<Vec<u8>>>;
+
+/// A dictionary that word completions are drawn from, and how much its word counts are worth
+struct DictionaryLayer {
+    weight: f64,
+    words: Arc<Map<Vec<u8>>>,
+}
+
 impl Predictor {
     const WORD_COUNT: usize = 25;
+    /// How many orders of magnitude of word frequency a typo costing 1 is worth
+    const TYPO_COST_WEIGHT: f64 = 3.0;
+    /// Autocorrect only replaces words at least this long, with a correction costing at most
+    /// `AUTOCORRECT_MAX_COST` that scores `AUTOCORRECT_MARGIN` more than any other
+    const AUTOCORRECT_MIN_LENGTH: usize = 3;
+    const AUTOCORRECT_MAX_COST: f64 = 1.0;
+    const AUTOCORRECT_MARGIN: f64 = 1.0;
+    const NAME_COUNT: usize = 100;
+    /// How many matching names are collected to pick the shortest `NAME_COUNT` from, since
+    /// matches come in key order rather than by length
+    const NAME_CANDIDATE_COUNT: usize = 5000;
+
+    /// Builds a predictor from the dictionary and symbol data bundles, checking that they're
+    /// intact and that every shortcode refers to symbols that exist
+    pub fn new(
+        dictionary_bytes: &[u8],
+        symbols_bytes: &[u8],
+    ) -> Result<Predictor, PredictionError> {
+        let dictionary =
+            Predictor::parse_dictionary(dictionary_bytes, &dictionary_bundle(DEFAULT_LOCALE))?;
+
+        let symbols_error = |err| InvalidBundle(SYMBOLS_BUNDLE.to_string(), err);
+        let symbols_bundle = Bundle::parse(symbols_bytes).map_err(symbols_error)?;
+        let shortcodes = symbols_bundle
+            .section(SHORTCODES_SECTION)
+            .map_err(symbols_error)?;
+        let symbols = symbols_bundle
+            .section(SYMBOLS_SECTION)
+            .map_err(symbols_error)?;
+
+        let shortcode_dictionary = Map::new(shortcodes.to_vec()).map_err(FstError)?;
+        let symbols: SymbolData =
+            bincode::deserialize(symbols).map_err(|err| InvalidSymbols(err.to_string()))?;
+        Predictor::validate_symbols(&shortcode_dictionary, &symbols)?;
+
+        Ok(Predictor::with_data(
+            dictionary,
+            shortcode_dictionary,
+            symbols,
+        ))
+    }
+
+    /// A predictor without any built-in data, so the engine still works if it can't be loaded
+    fn empty() -> Predictor {
+        Predictor::with_data(Map::default(), Map::default(), SymbolData::default())
+    }
+
+    fn with_data(
+        dictionary: Map<Vec<u8>>,
+        shortcode_dictionary: Map<Vec<u8>>,
+        symbols: SymbolData,
+    ) -> Predictor {
+        let mut dictionaries = Predictor::load_dictionaries(DICTIONARY_KIND);
+        dictionaries
+            .entry(DEFAULT_LOCALE.to_string())
+            .or_insert_with(|| Arc::new(dictionary));
+        Predictor {
+            dictionaries,
+            packs: Predictor::load_dictionaries(PACK_KIND),
+            phrases: Predictor::load_dictionaries(PHRASES_KIND),
+            user_words: Predictor::user_words(),
+            personal_dictionaries: Mutex::new(HashMap::new()),
+            hidden_words: Predictor::word_set(HIDDEN_WORDS_FILE),
+            misspellings: Predictor::parse_misspellings(include_str!("../../misspellings.tsv")),
+            autocorrect_exceptions: Predictor::word_set(AUTOCORRECT_EXCEPTIONS_FILE),
+            blocklist: Blocklist::parse(
+                include_str!("../../blocklist.tsv"),
+                CONFIG.blocklist.strictness,
+            ),
+            user_blocklist: Predictor::user_blocklist(),
+            shortcode_dictionary,
+            symbols,
+            unicode_names: Predictor::load_data_file(UNICODE_NAMES_BUNDLE, NAMES_SECTION),
+            latex_commands: Predictor::load_data_file(LATEX_COMMANDS_BUNDLE, COMMANDS_SECTION),
+            emoticons: Predictor::load_emoticons(),
+            snippets: Predictor::load_snippets(),
+            user_shortcodes: Predictor::user_shortcodes(),
+        }
+    }
+
+    fn validate_symbols(
+        shortcode_dictionary: &Map<Vec<u8>>,
+        symbols: &SymbolData,
+    ) -> Result<(), PredictionError> {
+        let mut stream = shortcode_dictionary.stream();
+        while let Some((key, ident)) = stream.next() {
+            let valid = symbols
+                .key_symbols
+                .get(ident as usize)
+                .map(|ids| ids.iter().all(|id| (*id as usize) < symbols.symbols.len()))
+                .unwrap_or(false);
+            if !valid {
+                return Err(MissingSymbol(
+                    String::from_utf8_lossy(key).to_string(),
+                    ident,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_dictionary(bytes: &[u8], filename: &str) -> Result<Map<Vec<u8>>, PredictionError> {
+        let bundle_error = |err| InvalidBundle(filename.to_string(), err);
+        let bundle = Bundle::parse(bytes).map_err(bundle_error)?;
+        let dictionary = bundle.section(DICTIONARY_SECTION).map_err(bundle_error)?;
+        Map::new(dictionary.to_vec()).map_err(FstError)
+    }
+
+    /// Dictionaries for other locales (`dictionary-<locale>.bundle`), word packs
+    /// (`pack-<name>.bundle`) and phrases (`phrases-<locale>.bundle`) are generated by preproc and
+    /// installed to the data directory. An installed dictionary for the default locale replaces the
+    /// built-in one.
+    fn load_dictionaries(kind: &str) -> BTreeMap<String, Arc<Map<Vec<u8>>>> {
+        let mut dictionaries = BTreeMap::new();
+        let entries = match data_dir().map(std::fs::read_dir) {
+            Ok(Ok(entries)) => entries,
+            _ => {
+                log::info!("No installed {} files found", kind);
+                return dictionaries;
+            }
+        };
+
+        let prefix = format!("{}-", kind);
+        for entry in entries.flatten() {
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            let name = match filename
+                .strip_prefix(prefix.as_str())
+                .and_then(|rest| rest.strip_suffix(".bundle"))
+            {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let dictionary = match std::fs::read(entry.path()) {
+                Ok(bytes) => Predictor::parse_dictionary(&bytes, &filename),
+                Err(err) => {
+                    log::error!("Failed to read {:?}: {}", entry.path(), err);
+                    continue;
+                }
+            };
+            match dictionary {
+                Ok(dictionary) => {
+                    log::info!("Loaded {} {} from {:?}", kind, name, entry.path());
+                    dictionaries.insert(name, Arc::new(dictionary));
+                }
+                Err(err) => log::error!("Failed to load {} {}: {}", kind, name, err),
+            }
+        }
+        dictionaries
+    }
+
+    /// Some data (unicode names, LaTeX commands) is generated locally by preproc and installed to
+    /// the data directory, so unlike the built-in data it may be missing
+    fn load_data_file(filename: &str, section: &str) -> Option<Map<Vec<u8>>> {
+        let path = match data_dir() {
+            Ok(dir) => dir.join(filename),
+            Err(err) => {
+                log::warn!("Could not locate data directory for {}: {}", filename, err);
+                return None;
+            }
+        };
+
+        match std::fs::read(&path) {
+            Ok(bytes) => Bundle::parse(&bytes)
+                .and_then(|bundle| bundle.section(section))
+                .map_err(|err| err.to_string())
+                .and_then(|fst| Map::new(fst.to_vec()).map_err(|err| err.to_string()))
+                .map_err(|err| log::error!("Invalid data in {:?}: {}", path, err))
+                .ok(),
+            Err(err) => {
+                log::info!("No data loaded from {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    /// Emoticons are read from `emoticons.tsv` in the config directory if the user has one,
+    /// otherwise the built-in table is used. Both have the same format as custom_shortcodes.tsv.
+    fn load_emoticons() -> BTreeMap<String, String> {
+        let user_emoticons = config_dir()
+            .map(|dir| dir.join(EMOTICONS_FILE))
+            .map_err(|err| log::warn!("Could not locate config directory: {}", err))
+            .ok()
+            .and_then(|path| match std::fs::read_to_string(&path) {
+                Ok(contents) => {
+                    log::info!("Loaded emoticons from {:?}", path);
+                    Some(contents)
+                }
+                Err(err) => {
+                    log::info!("No emoticons loaded from {:?}: {}", path, err);
+                    None
+                }
+            });
+
+        Predictor::parse_emoticons(
+            user_emoticons
+                .as_deref()
+                .unwrap_or(include_str!("../../emoticons.tsv")),
+        )
+    }
+
+    fn parse_emoticons(contents: &str) -> BTreeMap<String, String> {
+        Predictor::parse_entries(contents)
+            .into_iter()
+            .flat_map(|entry| {
+                let emoji = entry.symbol;
+                std::iter::once(entry.shortcode)
+                    .chain(entry.aliases)
+                    .map(move |emoticon| (emoticon, emoji.clone()))
+            })
+            .collect()
+    }
+
+    /// Parses a file in the format of custom_shortcodes.tsv, skipping invalid lines
+    fn parse_entries(contents: &str) -> Vec<ShortcodeEntry> {
+        parse_shortcode_entries(contents)
+            .filter_map(|result| {
+                result
+                    .map_err(|err| log::warn!("Skipping invalid entry on {}", err))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Parses user shortcodes into the same keys as the shortcode FST, each with its symbol
+    fn parse_user_shortcodes(contents: &str) -> BTreeMap<String, SymbolInfo> {
+        Predictor::parse_entries(contents)
+            .into_iter()
+            .flat_map(|entry| {
+                let info = entry.info();
+                entry.keys().into_iter().map(move |key| (key, info.clone()))
+            })
+            .collect()
+    }
+
+    /// Shortcodes from `shortcodes.tsv` in the config directory, which take effect as soon as the
+    /// file is saved
+    fn user_shortcodes() -> WatchedFile<BTreeMap<String, SymbolInfo>> {
+        let path = config_dir()
+            .map(|dir| dir.join(USER_SHORTCODES_FILE))
+            .map_err(|err| log::warn!("Could not locate config directory: {}", err))
+            .ok();
+        WatchedFile::new(path, Predictor::parse_user_shortcodes)
+    }
+
+    /// Builds a dictionary from a user word list, keeping the highest count of repeated words
+    fn parse_user_words(contents: &str) -> Map<Vec<u8>> {
+        let mut words: BTreeMap<String, u64> = BTreeMap::new();
+        for result in parse_word_counts(contents) {
+            match result {
+                Ok((word, count)) => {
+                    let existing = words.entry(word).or_insert(0);
+                    *existing = (*existing).max(count);
+                }
+                Err(err) => log::warn!("Skipping invalid word on {}", err),
+            }
+        }
+        Predictor::word_map(words)
+    }
+
+    fn word_map(words: BTreeMap<String, u64>) -> Map<Vec<u8>> {
+        Map::from_iter(words).unwrap_or_else(|err| {
+            log::error!("Failed to build user dictionary: {}", err);
+            Map::default()
+        })
+    }
+
+    /// Builds a dictionary from a hunspell or aspell personal word list, which has one word per
+    /// line. hunspell lines can have `/flags` after the word, or start with `*` for words it
+    /// should reject, and aspell lists start with a `personal_ws` header.
+    fn parse_personal_words(contents: &str) -> Map<Vec<u8>> {
+        let words: BTreeMap<String, u64> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| {
+                !line.is_empty() && !line.starts_with('*') && !line.starts_with("personal_ws")
+            })
+            .map(|line| {
+                let word = line.split('/').next().unwrap_or(line);
+                (word.to_lowercase(), DEFAULT_WORD_COUNT)
+            })
+            .collect();
+        Predictor::word_map(words)
+    }
+
+    /// `~/.hunspell_<locale>` and `~/.aspell.<language>.pws` (or `~/.aspell.<locale>.pws`)
+    fn personal_dictionary_files(locale: &str) -> Vec<WatchedDictionary> {
+        let home = match home_dir() {
+            Ok(home) => home,
+            Err(err) => {
+                log::warn!("Could not locate home directory: {}", err);
+                return Vec::new();
+            }
+        };
+        let language = locale.split('_').next().unwrap_or(locale);
+        let mut filenames = vec![
+            format!(".hunspell_{}", locale),
+            format!(".aspell.{}.pws", language),
+        ];
+        if language != locale {
+            filenames.push(format!(".aspell.{}.pws", locale));
+        }
+        filenames
+            .into_iter()
+            .map(|filename| {
+                WatchedFile::new(Some(home.join(filename)), Predictor::parse_personal_words)
+            })
+            .collect()
+    }
+
+    /// The current contents of the personal dictionaries for a locale
+    fn personal_dictionaries(&self, locale: &str) -> Vec<Arc<Map<Vec<u8>>>> {
+        let mut dictionaries = self
+            .personal_dictionaries
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        dictionaries
+            .entry(locale.to_string())
+            .or_insert_with(|| Predictor::personal_dictionary_files(locale))
+            .iter()
+            .map(|file| file.get())
+            .collect()
+    }
+
+    /// Words from `words.tsv` in the config directory, which take effect as soon as the file is
+    /// saved
+    fn user_words() -> WatchedDictionary {
+        let path = config_dir()
+            .map(|dir| dir.join(USER_WORDS_FILE))
+            .map_err(|err| log::warn!("Could not locate config directory: {}", err))
+            .ok();
+        WatchedFile::new(path, Predictor::parse_user_words)
+    }
+
+    /// A list of words in the config directory, like the hidden words
+    fn word_set(filename: &str) -> WatchedFile<HashSet<String>> {
+        let path = config_dir()
+            .map(|dir| dir.join(filename))
+            .map_err(|err| log::warn!("Could not locate config directory: {}", err))
+            .ok();
+        WatchedFile::new(path, parse_word_set)
+    }
+
+    /// Common misspellings and their corrections, which are always autocorrected
+    fn parse_misspellings(contents: &str) -> HashMap<String, String> {
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| match line.split_once('\t') {
+                Some((typo, correction)) => {
+                    Some((typo.trim().to_lowercase(), correction.trim().to_string()))
+                }
+                None => {
+                    log::warn!("Invalid misspelling line: {}", line);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Words in `blocklist.txt` in the config directory are blocked like those in the shipped
+    /// blocklist, and take effect as soon as the file is saved
+    fn user_blocklist() -> WatchedFile<Blocklist> {
+        let path = config_dir()
+            .map(|dir| dir.join(USER_BLOCKLIST_FILE))
+            .map_err(|err| log::warn!("Could not locate config directory: {}", err))
+            .ok();
+        WatchedFile::new(path, |contents| {
+            Blocklist::parse(contents, CONFIG.blocklist.strictness)
+        })
+    }
+
+    /// Snippets are read from `snippets.toml` in the config directory, which maps each
+    /// abbreviation to the text it expands to
+    fn load_snippets() -> BTreeMap<String, String> {
+        let path = match config_dir() {
+            Ok(dir) => dir.join(SNIPPETS_FILE),
+            Err(err) => {
+                log::warn!("Could not locate config directory: {}", err);
+                return BTreeMap::new();
+            }
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+                log::error!("Invalid snippets in {:?}: {}", path, err);
+                BTreeMap::new()
+            }),
+            Err(err) => {
+                log::info!("No snippets loaded from {:?}: {}", path, err);
+                BTreeMap::new()
+            }
+        }
+    }
 
     fn is_title_cased(context: &str) -> bool {
         let mut chars = context.chars();
 
         let first_letter_capitalized = chars
             .next()
-            .map(|char| char.is_ascii_uppercase())
+            .map(|char| char.is_uppercase())
             .unwrap_or(false);
-        let other_capitals: i8 = chars.map(|char| char.is_ascii_uppercase() as i8).sum();
+        let other_capitals: usize = chars.map(|char| char.is_uppercase() as usize).sum();
         first_letter_capitalized & (other_capitals == 0)
     }
 
     fn title_case(word: String) -> String {
         let mut chars = word.chars();
-        chars.next().unwrap().to_ascii_uppercase().to_string() + chars.as_str()
+        chars.next().unwrap().to_uppercase().to_string() + chars.as_str()
     }
 
-    pub fn word(&self, context: &str) -> Result<Vec<String>, PredictionError> {
-        let title_cased = Predictor::is_title_cased(context);
-        let lowercase_context = context.to_ascii_lowercase();
-        let matcher = Str::new(lowercase_context.as_str()).starts_with();
+    /// Makes the next search reread the word lists the engine itself writes to, so that adding or
+    /// hiding a word shows up straight away
+    pub fn reload_user_words(&self) {
+        self.user_words.invalidate();
+        self.hidden_words.invalidate();
+        self.autocorrect_exceptions.invalidate();
+    }
 
-        let mut search_results = self
-            .dictionary
-            .search(matcher)
-            .into_stream()
-            .into_str_vec()
-            .map_err(FstError)?;
+    /// Whether a dictionary for a locale is installed, rather than falling back to the built-in one
+    pub fn has_dictionary(&self, locale: &str) -> bool {
+        self.dictionaries.contains_key(locale)
+    }
 
-        search_results.sort_by(|(_w1, f1), (_w2, f2)| f2.cmp(f1));
+    /// The dictionary for a locale, falling back to the built-in one if it isn't installed
+    fn dictionary(&self, locale: &str) -> Arc<Map<Vec<u8>>> {
+        self.dictionaries
+            .get(locale)
+            .or_else(|| {
+                log::debug!("No {} dictionary, using {}", locale, DEFAULT_LOCALE);
+                self.dictionaries.get(DEFAULT_LOCALE)
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The layers words are completed from, in order: the locale's dictionary, its phrases if
+    /// they're installed, word packs by name, the user's words and their personal hunspell/aspell
+    /// dictionaries. Layers with a weight of 0 are left out.
+    fn dictionary_layers(&self, locale: &str) -> Vec<DictionaryLayer> {
+        let config = &CONFIG.dictionaries;
+        let base = DictionaryLayer {
+            weight: config.base_weight,
+            words: self.dictionary(locale),
+        };
+        let phrases = self.phrases.get(locale).map(|phrases| DictionaryLayer {
+            weight: config.phrase_weight,
+            words: phrases.clone(),
+        });
+        let packs = self.packs.iter().map(|(name, words)| DictionaryLayer {
+            weight: config.packs.get(name).copied().unwrap_or(1.0),
+            words: words.clone(),
+        });
+        let user = std::iter::once(self.user_words.get())
+            .chain(self.personal_dictionaries(locale))
+            .map(|words| DictionaryLayer {
+                weight: config.user_weight,
+                words,
+            });
+        std::iter::once(base)
+            .chain(phrases)
+            .chain(packs)
+            .chain(user)
+            .filter(|layer| layer.weight > 0.0)
+            .collect()
+    }
+
+    /// Words in any of a locale's dictionary layers matched by `automaton`, each with its count
+    /// times the weight of the layer it's worth the most in. Hidden and blocked words are left out.
+    fn layered_search<A: Automaton>(&self, locale: &str, automaton: A) -> Vec<(String, f64)> {
+        let layers = self.dictionary_layers(locale);
+        let mut union = OpBuilder::new();
+        for layer in layers.iter() {
+            union.push(layer.words.search(&automaton));
+        }
+
+        let hidden_words = self.hidden_words.get();
+        let user_blocklist = self.user_blocklist.get();
+        let mut search_results: Vec<(String, f64)> = Vec::new();
+        let mut stream = union.union();
+        while let Some((word, values)) = stream.next() {
+            let word = String::from_utf8_lossy(word).into_owned();
+            //phrases are blocked if any of their words are
+            if hidden_words.contains(&word)
+                || word
+                    .split(' ')
+                    .any(|part| self.blocklist.is_blocked(part) || user_blocklist.is_blocked(part))
+            {
+                continue;
+            }
+            let score = values
+                .iter()
+                .map(|value| layers[value.index].weight * value.value as f64)
+                .fold(0.0, f64::max);
+            search_results.push((word, score));
+        }
+        search_results
+    }
+
+    pub fn word(&self, locale: &str, context: &str) -> Result<Vec<String>, PredictionError> {
+        let title_cased = Predictor::is_title_cased(context);
+        let lowercase_context = context.to_lowercase();
+
+        let mut search_results =
+            self.layered_search(locale, Str::new(lowercase_context.as_str()).starts_with());
+        search_results.sort_by(|(_w1, s1), (_w2, s2)| s2.total_cmp(s1));
         let final_results = search_results
             .into_iter()
             .map(|(word, _freq)| {
@@ -73,7 +616,103 @@ impl Predictor {
         Ok(final_results)
     }
 
-    pub fn symbol(&self, context: &str) -> Result<Vec<(String, String)>, PredictionError> {
+    /// Whole words that `typed` may be a misspelling of, most likely first. Words within two edits
+    /// (one for very short words) are ranked by their frequency, lowered by `TYPO_COST_WEIGHT`
+    /// orders of magnitude for each unit of typo cost.
+    pub fn corrections(&self, locale: &str, typed: &str) -> Result<Vec<String>, PredictionError> {
+        let title_cased = Predictor::is_title_cased(typed);
+        Ok(self
+            .scored_corrections(locale, &typed.to_lowercase())?
+            .into_iter()
+            .map(|(word, _score)| {
+                if title_cased {
+                    Predictor::title_case(word)
+                } else {
+                    word
+                }
+            })
+            .take(Predictor::WORD_COUNT)
+            .collect())
+    }
+
+    fn scored_corrections(
+        &self,
+        locale: &str,
+        lowercase_typed: &str,
+    ) -> Result<Vec<(String, f64)>, PredictionError> {
+        let distance = if lowercase_typed.chars().count() <= 2 {
+            1
+        } else {
+            2
+        };
+        let automaton = Levenshtein::new(lowercase_typed, distance).map_err(LevenshteinError)?;
+
+        let mut search_results: Vec<(String, f64)> = self
+            .layered_search(locale, automaton)
+            .into_iter()
+            .filter(|(word, _count)| !word.contains(' '))
+            .map(|(word, count)| {
+                let score = (count + 1.0).log10()
+                    - Predictor::TYPO_COST_WEIGHT * typo_cost(lowercase_typed, &word);
+                (word, score)
+            })
+            .collect();
+        search_results.sort_by(|(_w1, s1), (_w2, s2)| s2.total_cmp(s1));
+        Ok(search_results)
+    }
+
+    /// What to replace a word that was just typed with when autocorrecting, if anything. Words in
+    /// the built-in list of common misspellings are always corrected. Other words are corrected
+    /// if they aren't in any dictionary and one correction is much more likely than the others.
+    pub fn autocorrection(&self, locale: &str, typed: &str) -> Option<String> {
+        let lowercase_typed = typed.to_lowercase();
+        if self.autocorrect_exceptions.get().contains(&lowercase_typed) {
+            return None;
+        }
+
+        let correction = match self.misspellings.get(&lowercase_typed) {
+            Some(correction) => correction.clone(),
+            None => {
+                if lowercase_typed.chars().count() < Predictor::AUTOCORRECT_MIN_LENGTH
+                    || !lowercase_typed.chars().all(char::is_alphabetic)
+                    || self.is_known(locale, &lowercase_typed)
+                {
+                    return None;
+                }
+                let corrections = self
+                    .scored_corrections(locale, &lowercase_typed)
+                    .map_err(|err| log::error!("{}", err))
+                    .ok()?;
+                let (best, best_score) = corrections.first()?;
+                let clear_winner = corrections.get(1).is_none_or(|(_word, score)| {
+                    best_score - score >= Predictor::AUTOCORRECT_MARGIN
+                });
+                if !clear_winner
+                    || typo_cost(&lowercase_typed, best) > Predictor::AUTOCORRECT_MAX_COST
+                {
+                    return None;
+                }
+                best.clone()
+            }
+        };
+
+        if Predictor::is_title_cased(typed) {
+            Some(Predictor::title_case(correction))
+        } else {
+            Some(correction)
+        }
+    }
+
+    /// Whether a lowercase word is in any of a locale's dictionary layers
+    fn is_known(&self, locale: &str, word: &str) -> bool {
+        self.dictionary_layers(locale)
+            .iter()
+            .any(|layer| layer.words.contains_key(word))
+    }
+
+    /// Returns (shortcode, symbol) pairs for shortcodes or symbol keywords starting with the
+    /// context. Shortcodes with several symbols have one pair per symbol, in priority order.
+    pub fn symbol(&self, context: &str) -> Result<Vec<(String, SymbolInfo)>, PredictionError> {
         let matcher = Str::new(context).starts_with();
         let search_results = self
             .shortcode_dictionary
@@ -82,46 +721,255 @@ impl Predictor {
             .into_str_vec()
             .map_err(FstError)?;
 
-        //must be into_iter() and not iter() - the latter iterates over references, but we need
-        //to take ownership to return the shortcode data without clone()
+        //keyword keys are shown as the shortcode they belong to, so several keys can give the
+        //same result
+        let mut symbols: BTreeMap<String, Vec<SymbolInfo>> = BTreeMap::new();
+        for (key, ident) in search_results {
+            let ids = self
+                .symbols
+                .key_symbols
+                .get(ident as usize)
+                .ok_or_else(|| MissingSymbol(key.clone(), ident))?;
+            let shortcode_symbols = symbols.entry(key_shortcode(&key).to_string()).or_default();
+            for id in ids {
+                let info = self
+                    .symbols
+                    .symbols
+                    .get(*id as usize)
+                    .ok_or_else(|| MissingSymbol(key.clone(), *id as u64))?;
+                if !shortcode_symbols.contains(info) {
+                    shortcode_symbols.push(info.clone());
+                }
+            }
+        }
+
+        //user shortcodes replace built-in ones with the same name
+        let user_shortcodes = self.user_shortcodes.get();
+        for (key, info) in user_shortcodes
+            .range::<str, _>((Included(context), Unbounded))
+            .take_while(|(key, _info)| key.starts_with(context))
+        {
+            symbols.insert(key_shortcode(key).to_string(), vec![info.clone()]);
+        }
+
+        Ok(symbols
+            .into_iter()
+            .flat_map(|(shortcode, infos)| {
+                infos.into_iter().map(move |info| (shortcode.clone(), info))
+            })
+            .collect())
+    }
+
+    /// Returns (command, symbol) pairs for LaTeX commands starting with the context, shortest
+    /// commands first so that exact matches come before longer commands
+    pub fn latex(&self, context: &str) -> Result<Vec<(String, String)>, PredictionError> {
+        let latex_commands = self
+            .latex_commands
+            .as_ref()
+            .ok_or(MissingData(LATEX_COMMANDS_BUNDLE))?;
+
+        let matcher = Str::new(context).starts_with();
+        let mut search_results = latex_commands
+            .search(matcher)
+            .into_stream()
+            .into_str_vec()
+            .map_err(FstError)?;
+
+        search_results.sort_by(|(c1, _s1), (c2, _s2)| c1.len().cmp(&c2.len()).then(c1.cmp(c2)));
         search_results
             .into_iter()
             .map(
-                |(shortcode, ident)| match self.symbols.get(ident as usize) {
-                    Some(symbol) => Ok((shortcode, symbol.clone())),
-                    None => Err(MissingSymbol(shortcode, ident)),
+                |(command, codepoint)| match char::from_u32(codepoint as u32) {
+                    Some(symbol) => Ok((command, symbol.to_string())),
+                    None => Err(MissingSymbol(command, codepoint)),
                 },
             )
             .collect::<Result<Vec<_>, _>>()
     }
+
+    pub fn latex_symbol(&self, command: &str) -> Option<String> {
+        self.latex_commands
+            .as_ref()
+            .and_then(|latex_commands| latex_commands.get(command))
+            .and_then(|codepoint| char::from_u32(codepoint as u32))
+            .map(|symbol| symbol.to_string())
+    }
+
+    /// The emoji for an emoticon typed as a word of its own. While a parenthesis is open,
+    /// emoticons ending in `)` are left alone since they're more likely closing it, like the `B)`
+    /// in "(see appendix B)".
+    pub fn emoticon(&self, text: &str, open_parentheses: usize) -> Option<&str> {
+        if open_parentheses > 0 && text.ends_with(')') {
+            return None;
+        }
+        self.emoticons.get(text).map(|emoji| emoji.as_str())
+    }
+
+    /// Whether some longer emoticon starts with `text`, in which case it shouldn't be converted yet
+    pub fn is_emoticon_prefix(&self, text: &str) -> bool {
+        self.emoticons
+            .range::<str, _>((Excluded(text), Unbounded))
+            .next()
+            .map(|(emoticon, _emoji)| emoticon.starts_with(text))
+            .unwrap_or(false)
+    }
+
+    pub fn snippet(&self, abbreviation: &str) -> Option<&str> {
+        self.snippets.get(abbreviation).map(|text| text.as_str())
+    }
+
+    /// Snippets with abbreviations starting with the context, as (abbreviation, text) pairs
+    pub fn snippets(&self, context: &str) -> Vec<(String, String)> {
+        self.snippets
+            .range::<str, _>((Included(context), Unbounded))
+            .take_while(|(abbreviation, _text)| abbreviation.starts_with(context))
+            .map(|(abbreviation, text)| (abbreviation.clone(), text.clone()))
+            .collect()
+    }
+
+    pub fn has_unicode_names(&self) -> bool {
+        self.unicode_names.is_some()
+    }
+
+    /// Search every named character for names containing all words in the context (as word
+    /// prefixes, in any order). Returns (name, symbol) pairs, shortest names first.
+    pub fn unicode_name(&self, context: &str) -> Result<Vec<(String, String)>, PredictionError> {
+        let unicode_names = self
+            .unicode_names
+            .as_ref()
+            .ok_or(MissingData(UNICODE_NAMES_BUNDLE))?;
+
+        let lowercase_context = context.to_lowercase();
+        let words: Vec<&str> = lowercase_context
+            .split(is_name_word_separator)
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        //the FST contains every word-suffix of every name, so search for the most specific word
+        //and check the rest against the full name
+        let longest_word = match words.iter().max_by_key(|word| word.len()) {
+            Some(word) => word,
+            None => return Ok(Vec::new()),
+        };
+        let matcher = Str::new(longest_word).starts_with();
+        let mut stream = unicode_names.search(matcher).into_stream();
+
+        //short words match a huge number of keys, so stop once there are plenty of names to pick
+        //the shortest from
+        let mut seen_codepoints = HashSet::new();
+        let mut matches: Vec<(String, u64)> = Vec::new();
+        while let Some((key, codepoint)) = stream.next() {
+            let name = match std::str::from_utf8(key)
+                .ok()
+                .and_then(|key| key.split(NAME_SEPARATOR).nth(1))
+            {
+                Some(name) => name,
+                None => continue,
+            };
+            let matches_all_words = words.iter().all(|word| {
+                name.split(is_name_word_separator)
+                    .any(|name_word| name_word.starts_with(word))
+            });
+            if matches_all_words && seen_codepoints.insert(codepoint) {
+                matches.push((name.to_string(), codepoint));
+                if matches.len() == Predictor::NAME_CANDIDATE_COUNT {
+                    break;
+                }
+            }
+        }
+
+        matches.sort_by(|(n1, _c1), (n2, _c2)| n1.len().cmp(&n2.len()).then(n1.cmp(n2)));
+        matches
+            .into_iter()
+            .take(Predictor::NAME_COUNT)
+            .map(|(name, codepoint)| match char::from_u32(codepoint as u32) {
+                Some(symbol) => Ok((name, symbol.to_string())),
+                None => Err(MissingSymbol(name, codepoint)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+    }
 }
 
 lazy_static! {
-    pub static ref PREDICTOR: Predictor = Predictor {
-        dictionary: Map::new(include_bytes!("../../dictionary.fst").to_vec()).unwrap(),
-        shortcode_dictionary: Map::new(include_bytes!("../../shortcodes.fst").to_vec()).unwrap(),
-        symbols: bincode::deserialize(include_bytes!("../../symbols.bin")).unwrap()
-    };
+    pub static ref PREDICTOR: Predictor = Predictor::new(
+        include_bytes!("../../dictionary-en_US.bundle"),
+        include_bytes!("../../symbols.bundle")
+    )
+    .unwrap_or_else(|err| {
+        log::error!(
+            "Failed to load built-in data, continuing without it: {}",
+            err
+        );
+        Predictor::empty()
+    });
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::PREDICTOR;
+    use crate::blocklist::Blocklist;
+    use crate::config::{SmartPunctuationConfig, Strictness};
+    use crate::predict::{Predictor, USER_BLOCKLIST_FILE};
+    use crate::typography::smart_punctuation;
+    use crate::user_store::{
+        parse_word_set, AUTOCORRECT_EXCEPTIONS_FILE, HIDDEN_WORDS_FILE, USER_WORDS_FILE,
+    };
+    use crate::watched::WatchedFile;
+    use common::bundle::DEFAULT_LOCALE;
+    use fst::Map;
+    use std::path::Path;
+    use std::sync::Arc;
 
-    fn symbol_test(head: &str) {
-        let symbol_results = PREDICTOR.symbol(head).unwrap();
+    /// A predictor with only the bundled data, which reads the user's files from `dir` rather than
+    /// the real config directory
+    fn isolated_predictor(dir: &Path) -> Predictor {
+        let mut predictor = Predictor::new(
+            include_bytes!("../../dictionary-en_US.bundle"),
+            include_bytes!("../../symbols.bundle"),
+        )
+        .unwrap();
+        let dictionary = Predictor::parse_dictionary(
+            include_bytes!("../../dictionary-en_US.bundle"),
+            "dictionary-en_US.bundle",
+        )
+        .unwrap();
+        predictor.dictionaries.clear();
+        predictor
+            .dictionaries
+            .insert(DEFAULT_LOCALE.to_string(), Arc::new(dictionary));
+        predictor.packs.clear();
+        predictor.phrases.clear();
+        predictor
+            .personal_dictionaries
+            .lock()
+            .unwrap()
+            .insert(DEFAULT_LOCALE.to_string(), Vec::new());
+
+        predictor.user_words =
+            WatchedFile::new(Some(dir.join(USER_WORDS_FILE)), Predictor::parse_user_words);
+        predictor.hidden_words =
+            WatchedFile::new(Some(dir.join(HIDDEN_WORDS_FILE)), parse_word_set);
+        predictor.autocorrect_exceptions =
+            WatchedFile::new(Some(dir.join(AUTOCORRECT_EXCEPTIONS_FILE)), parse_word_set);
+        predictor.user_blocklist = WatchedFile::new(Some(dir.join(USER_BLOCKLIST_FILE)), |_| {
+            Blocklist::default()
+        });
+        predictor
+    }
+
+    fn symbol_test(predictor: &Predictor, head: &str) {
+        let symbol_results = predictor.symbol(head).unwrap();
         println!("symbols for {head}", head = head);
-        for (shortcode, symbol) in symbol_results {
+        for (shortcode, info) in symbol_results {
             println!(
                 "{shortcode} : {symbol}",
                 shortcode = shortcode,
-                symbol = symbol
+                symbol = info.symbol
             );
         }
     }
 
-    fn word_test(head: &str) {
-        let word_results = PREDICTOR.word(head).unwrap();
+    fn word_test(predictor: &Predictor, head: &str) {
+        let word_results = predictor.word(DEFAULT_LOCALE, head).unwrap();
 
         println!("words for {head}:", head = head);
         for word in word_results {
@@ -129,13 +977,225 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bundled_data() {
+        let predictor = Predictor::new(
+            include_bytes!("../../dictionary-en_US.bundle"),
+            include_bytes!("../../symbols.bundle"),
+        );
+        assert!(predictor.is_ok());
+        assert!(Predictor::new(b"", include_bytes!("../../symbols.bundle")).is_err());
+    }
+
+    #[test]
+    fn dictionary_layers() {
+        let dir =
+            std::env::temp_dir().join(format!("eei_dictionary_layers_{}", std::process::id()));
+        let mut predictor = isolated_predictor(&dir);
+        let pack = Map::from_iter(vec![("litellm", 1_000_000_000_000), ("little", 1)]).unwrap();
+        predictor.packs.insert("test".to_string(), Arc::new(pack));
+
+        let words = predictor.word(DEFAULT_LOCALE, "lit").unwrap();
+        assert_eq!(words[0], "litellm");
+        assert_eq!(words.iter().filter(|word| *word == "little").count(), 1);
+    }
+
+    #[test]
+    fn phrases() {
+        let dir = std::env::temp_dir().join(format!("eei_phrases_{}", std::process::id()));
+        let mut predictor = isolated_predictor(&dir);
+        let phrases = Map::from_iter(vec![
+            ("let me know", 1_000_000_000_000),
+            ("let the shit", 1_000_000_000_000),
+        ])
+        .unwrap();
+        predictor
+            .phrases
+            .insert(DEFAULT_LOCALE.to_string(), Arc::new(phrases));
+
+        let words = predictor.word(DEFAULT_LOCALE, "Le").unwrap();
+        assert_eq!(words[0], "Let me know");
+        assert!(!words.iter().any(|word| word.ends_with("shit")));
+        assert!(!predictor
+            .word("en_GB", "le")
+            .unwrap()
+            .contains(&"let me know".to_string()));
+    }
+
+    #[test]
+    fn corrections() {
+        let dir = std::env::temp_dir().join(format!("eei_corrections_{}", std::process::id()));
+        let predictor = isolated_predictor(&dir);
+        let corrections = predictor.corrections(DEFAULT_LOCALE, "Teh").unwrap();
+        assert_eq!(corrections[0], "The");
+        assert_eq!(
+            predictor.corrections(DEFAULT_LOCALE, "recieve").unwrap()[0],
+            "receive"
+        );
+    }
+
+    #[test]
+    fn emoticons() {
+        let dir = std::env::temp_dir().join(format!("eei_emoticons_{}", std::process::id()));
+        let mut predictor = isolated_predictor(&dir);
+        predictor.emoticons = Predictor::parse_emoticons(include_str!("../../emoticons.tsv"));
+
+        assert_eq!(predictor.emoticon(":)", 0), Some("🙂"));
+        assert_eq!(predictor.emoticon("B)", 0), Some("😎"));
+        //"(see appendix B)" and "(that was fun :)"
+        assert_eq!(predictor.emoticon("B)", 1), None);
+        assert_eq!(predictor.emoticon(":)", 1), None);
+        assert_eq!(predictor.emoticon(":D", 1), Some("😃"));
+        //emoticons that are also words aren't in the built-in table
+        assert_eq!(predictor.emoticon("XD", 0), None);
+        assert!(predictor
+            .emoticons
+            .keys()
+            .all(|emoticon| !emoticon.chars().all(char::is_alphanumeric)));
+    }
+
+    #[test]
+    fn emoticons_with_smart_punctuation() {
+        let dir = std::env::temp_dir().join(format!("eei_emoticon_quotes_{}", std::process::id()));
+        let mut predictor = isolated_predictor(&dir);
+        predictor.emoticons = Predictor::parse_emoticons(include_str!("../../emoticons.tsv"));
+        let config = SmartPunctuationConfig::default();
+
+        //type each character like the engine does, applying any smart punctuation
+        let typed = |text: &str| {
+            let mut typed = String::new();
+            for next in text.chars() {
+                match smart_punctuation(&config, &typed, next, |text| {
+                    predictor.is_emoticon_prefix(text)
+                }) {
+                    Some(replacement) => {
+                        for _ in 0..replacement.delete {
+                            typed.pop();
+                        }
+                        typed.push_str(replacement.text);
+                    }
+                    None => typed.push(next),
+                }
+            }
+            typed
+        };
+        assert_eq!(predictor.emoticon(&typed(":'("), 0), Some("😢"));
+        assert_eq!(typed("don't"), "don’t");
+    }
+
+    #[test]
+    fn autocorrection() {
+        let dir = std::env::temp_dir().join(format!("eei_autocorrection_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let predictor = isolated_predictor(&dir);
+        let autocorrection = |typed| predictor.autocorrection(DEFAULT_LOCALE, typed);
+
+        assert_eq!(autocorrection("Teh"), Some("The".to_string()));
+        assert_eq!(autocorrection("becuase"), Some("because".to_string()));
+        assert_eq!(autocorrection("langauge"), Some("language".to_string()));
+        assert_eq!(autocorrection("the"), None);
+        assert_eq!(autocorrection("xqzv"), None);
+
+        std::fs::write(dir.join(AUTOCORRECT_EXCEPTIONS_FILE), "Becuase\n").unwrap();
+        predictor.reload_user_words();
+        let excepted = autocorrection("becuase");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(excepted, None);
+    }
+
+    #[test]
+    fn hidden_words() {
+        let dir = std::env::temp_dir().join(format!("eei_hidden_words_{}", std::process::id()));
+        let predictor = isolated_predictor(&dir);
+        let top = predictor.word(DEFAULT_LOCALE, "lit").unwrap()[0].clone();
+
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(HIDDEN_WORDS_FILE);
+        std::fs::write(&path, format!("# hidden\n{}\n", top.to_uppercase())).unwrap();
+        predictor.reload_user_words();
+        let words = predictor.word(DEFAULT_LOCALE, "Lit").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!words.is_empty());
+        assert!(!words.iter().any(|word| word.to_lowercase() == top));
+    }
+
+    #[test]
+    fn blocked_words() {
+        //the directory doesn't exist, so there are no user files
+        let dir = std::env::temp_dir().join(format!("eei_blocked_words_{}", std::process::id()));
+        let mut predictor = isolated_predictor(&dir);
+        let pack = Map::from_iter(vec![
+            ("bitches", 1_000_000_000_000),
+            ("bloody", 1_000_000_000_000),
+        ])
+        .unwrap();
+        predictor.packs.insert("test".to_string(), Arc::new(pack));
+
+        let mut suggested = |strictness| {
+            predictor.blocklist = Blocklist::parse(include_str!("../../blocklist.tsv"), strictness);
+            let mut words = predictor.word(DEFAULT_LOCALE, "bi").unwrap();
+            words.extend(predictor.word(DEFAULT_LOCALE, "blo").unwrap());
+            (
+                words.contains(&"bitches".to_string()),
+                words.contains(&"bloody".to_string()),
+            )
+        };
+        assert_eq!(suggested(Strictness::Off), (true, true));
+        //"bitch*" is strong and "bloody" mild
+        assert_eq!(suggested(Strictness::Normal), (false, true));
+        assert_eq!(suggested(Strictness::Strict), (false, false));
+    }
+
+    #[test]
+    fn personal_dictionaries() {
+        let aspell = Predictor::parse_personal_words("personal_ws-1.1 en 2\nKubectl\nrustup\n");
+        assert_eq!(aspell.len(), 2);
+        assert!(aspell.contains_key("kubectl"));
+
+        let hunspell = Predictor::parse_personal_words("foobar/Z\n*colour\n\n");
+        assert_eq!(hunspell.len(), 1);
+        assert!(hunspell.contains_key("foobar"));
+    }
+
+    #[test]
+    fn unicode_names() {
+        let dir = std::env::temp_dir().join(format!("eei_unicode_names_{}", std::process::id()));
+        let mut predictor = isolated_predictor(&dir);
+        //the long names' keys all come before the short name's in the FST
+        let mut keys: Vec<(String, u64)> = (0..150)
+            .map(|idx| {
+                let name = format!("heart with ribbon {:03}", idx);
+                (format!("{}\0{}", name, name), 0x1000 + idx)
+            })
+            .collect();
+        keys.push(("hearts\0hearts".to_string(), 0x2665));
+        predictor.unicode_names = Some(Map::from_iter(keys).unwrap());
+
+        let names = predictor.unicode_name("hear").unwrap();
+        assert_eq!(names.len(), Predictor::NAME_COUNT);
+        assert_eq!(names[0], ("hearts".to_string(), "♥".to_string()));
+    }
+
+    #[test]
+    fn missing_locale() {
+        let dir = std::env::temp_dir().join(format!("eei_missing_locale_{}", std::process::id()));
+        let predictor = isolated_predictor(&dir);
+        assert_eq!(
+            predictor.word("xx_XX", "lit").unwrap(),
+            predictor.word(DEFAULT_LOCALE, "lit").unwrap()
+        );
+    }
+
     #[test]
     fn main() {
-        symbol_test("eq");
-        symbol_test("u");
-        word_test("lit");
-        word_test("ang");
-        word_test("Lit");
-        word_test("LiT");
+        let dir = std::env::temp_dir().join(format!("eei_main_{}", std::process::id()));
+        let predictor = isolated_predictor(&dir);
+        symbol_test(&predictor, "eq");
+        symbol_test(&predictor, "u");
+        word_test(&predictor, "lit");
+        word_test(&predictor, "ang");
+        word_test(&predictor, "Lit");
+        word_test(&predictor, "LiT");
     }
 }
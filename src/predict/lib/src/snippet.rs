@@ -0,0 +1,156 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, NaiveDateTime};
+
+static CURSOR: &str = "cursor";
+const PREVIEW_LENGTH: usize = 20;
+
+/// A snippet with its placeholders filled in
+#[derive(Debug, PartialEq)]
+pub struct Expansion {
+    pub text: String,
+    /// Number of characters after the first `{cursor}` marker, which the cursor is moved back by
+    /// once the text has been inserted
+    pub cursor_offset: Option<usize>,
+}
+
+/// Fill in the placeholders of a snippet:
+/// `{date}`, `{time}`, `{datetime}`, `{date:<strftime format>}`, `{uuid}` and `{cursor}`.
+/// Anything else in braces is left as-is.
+pub fn expand(template: &str) -> Expansion {
+    fill(template, &Local::now().naive_local())
+}
+
+fn fill(template: &str, now: &NaiveDateTime) -> Expansion {
+    let mut text = String::with_capacity(template.len());
+    let mut cursor = None;
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+
+        let name = &rest[1..end];
+        if name == CURSOR {
+            if cursor.is_none() {
+                cursor = Some(text.chars().count());
+            }
+        } else if let Some(value) = placeholder_value(name, now) {
+            text.push_str(&value);
+        } else {
+            //not a placeholder, so only the brace is kept as-is
+            text.push('{');
+            rest = &rest[1..];
+            continue;
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+
+    let cursor_offset = cursor.map(|position| text.chars().count() - position);
+    Expansion {
+        text,
+        cursor_offset,
+    }
+}
+
+fn placeholder_value(name: &str, now: &NaiveDateTime) -> Option<String> {
+    match name {
+        "date" => format_time(now, "%Y-%m-%d"),
+        "time" => format_time(now, "%H:%M"),
+        "datetime" => format_time(now, "%Y-%m-%d %H:%M"),
+        "uuid" => uuid(),
+        _ => name
+            .strip_prefix("date:")
+            .and_then(|format| format_time(now, format)),
+    }
+}
+
+fn format_time(now: &NaiveDateTime, format: &str) -> Option<String> {
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        log::warn!("Invalid date format in snippet: {}", format);
+        return None;
+    }
+    Some(now.format_with_items(items.into_iter()).to_string())
+}
+
+/// A random (version 4) UUID
+fn uuid() -> Option<String> {
+    let mut bytes = [0u8; 16];
+    if let Err(err) = getrandom::getrandom(&mut bytes) {
+        log::error!("Failed to generate UUID: {}", err);
+        return None;
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+/// The first line of a snippet, shortened for display in the symbol table
+pub fn preview(template: &str) -> String {
+    let first_line = template.lines().next().unwrap_or("");
+    let mut preview: String = first_line.chars().take(PREVIEW_LENGTH).collect();
+    let shortened = first_line.chars().count() > PREVIEW_LENGTH;
+    let more_lines = template.lines().skip(1).any(|line| !line.trim().is_empty());
+    if shortened || more_lines {
+        preview.push('…');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::snippet::{fill, preview, Expansion};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn placeholders() {
+        let now = NaiveDate::from_ymd_opt(2021, 3, 14)
+            .unwrap()
+            .and_hms_opt(15, 9, 26)
+            .unwrap();
+        assert_eq!(
+            fill("Date: {date}, {time}\n{date:%d/%m}", &now),
+            Expansion {
+                text: "Date: 2021-03-14, 15:09\n14/03".to_string(),
+                cursor_offset: None
+            }
+        );
+        assert_eq!(
+            fill("<b>{cursor}</b> {nothing} {date:%Q} {", &now),
+            Expansion {
+                text: "<b></b> {nothing} {date:%Q} {".to_string(),
+                cursor_offset: Some(26)
+            }
+        );
+
+        let uuid = fill("{uuid}", &now).text;
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+    }
+
+    #[test]
+    fn previews() {
+        assert_eq!(preview("short"), "short");
+        assert_eq!(preview("short\n"), "short");
+        assert_eq!(preview("café"), "café");
+        assert_eq!(preview("Best regards,\nMe"), "Best regards,…");
+        assert_eq!(
+            preview("a very long single line snippet"),
+            "a very long single l…"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//QWERTY letter rows and how far each is shifted right of the top row, in keys
+static KEYBOARD_ROWS: [(&str, f64); 3] =
+    [("qwertyuiop", 0.0), ("asdfghjkl", 0.25), ("zxcvbnm", 0.75)];
+
+const INSERTION_COST: f64 = 1.0;
+const DELETION_COST: f64 = 1.0;
+const SUBSTITUTION_COST: f64 = 1.0;
+/// Hitting a key next to the right one is the most common kind of typo
+const ADJACENT_SUBSTITUTION_COST: f64 = 0.5;
+/// Swapping two letters, like "teh" for "the"
+const TRANSPOSITION_COST: f64 = 0.5;
+
+fn key_position(c: char) -> Option<(f64, f64)> {
+    KEYBOARD_ROWS
+        .iter()
+        .enumerate()
+        .find_map(|(row, (keys, offset))| {
+            keys.chars()
+                .position(|key| key == c)
+                .map(|column| (row as f64, column as f64 + offset))
+        })
+}
+
+/// Whether two letters are next to each other on a QWERTY keyboard, including diagonally
+fn is_adjacent(a: char, b: char) -> bool {
+    match (key_position(a), key_position(b)) {
+        (Some((row_a, column_a)), Some((row_b, column_b))) => {
+            let distance = (column_a - column_b).abs();
+            (row_a == row_b && distance == 1.0) || ((row_a - row_b).abs() == 1.0 && distance < 1.0)
+        }
+        _ => false,
+    }
+}
+
+/// How unlikely it is that `word` was typed as `typed`: an edit distance where substituting
+/// adjacent keys and transposing letters are cheaper than other edits. Both should be lowercase.
+pub fn typo_cost(typed: &str, word: &str) -> f64 {
+    let typed: Vec<char> = typed.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+
+    //costs[i][j] is the cost of typing the first j letters of word as the first i of typed
+    let mut costs = vec![vec![0.0; word.len() + 1]; typed.len() + 1];
+    for (i, row) in costs.iter_mut().enumerate() {
+        row[0] = i as f64 * INSERTION_COST;
+    }
+    for (j, cost) in costs[0].iter_mut().enumerate() {
+        *cost = j as f64 * DELETION_COST;
+    }
+
+    for i in 1..=typed.len() {
+        for j in 1..=word.len() {
+            let substitution = if typed[i - 1] == word[j - 1] {
+                0.0
+            } else if is_adjacent(typed[i - 1], word[j - 1]) {
+                ADJACENT_SUBSTITUTION_COST
+            } else {
+                SUBSTITUTION_COST
+            };
+            let mut cost = (costs[i - 1][j - 1] + substitution)
+                .min(costs[i - 1][j] + INSERTION_COST)
+                .min(costs[i][j - 1] + DELETION_COST);
+            if i > 1
+                && j > 1
+                && typed[i - 1] == word[j - 2]
+                && typed[i - 2] == word[j - 1]
+                && typed[i - 1] != typed[i - 2]
+            {
+                cost = cost.min(costs[i - 2][j - 2] + TRANSPOSITION_COST);
+            }
+            costs[i][j] = cost;
+        }
+    }
+    costs[typed.len()][word.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spelling::typo_cost;
+
+    #[test]
+    fn typo_costs() {
+        assert_eq!(typo_cost("the", "the"), 0.0);
+        assert_eq!(typo_cost("teh", "the"), 0.5);
+        //r is next to e, but p isn't
+        assert_eq!(typo_cost("thr", "the"), 0.5);
+        assert_eq!(typo_cost("thp", "the"), 1.0);
+        assert_eq!(typo_cost("th", "the"), 1.0);
+        assert_eq!(typo_cost("thee", "the"), 1.0);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::config::SmartPunctuationConfig;
+
+/// Replace the last `delete` characters already typed, plus the character being typed, with `text`
+#[derive(Debug, PartialEq)]
+pub struct Replacement {
+    pub delete: usize,
+    pub text: &'static str,
+}
+
+/// Quotes typed at the start of the text, after whitespace or after opening punctuation open a
+/// quotation, and anywhere else they close one
+fn opens_quote(preceding: &str) -> bool {
+    preceding
+        .chars()
+        .last()
+        .map(|c| c.is_whitespace() || "([{<—–‘“".contains(c))
+        .unwrap_or(true)
+}
+
+/// How `next` should be typed given the text typed before it, or None if it should be typed as-is.
+/// Characters that could be part of an emoticon, like the `'` in `:'(`, are left alone so the
+/// emoticon can still be converted.
+pub fn smart_punctuation(
+    config: &SmartPunctuationConfig,
+    preceding: &str,
+    next: char,
+    is_emoticon_prefix: impl Fn(&str) -> bool,
+) -> Option<Replacement> {
+    if is_emoticon_prefix(&format!("{}{}", preceding, next)) {
+        return None;
+    }
+
+    let replace = |delete, text| Some(Replacement { delete, text });
+    match next {
+        '-' if config.dashes && preceding.ends_with('-') => replace(1, "—"),
+        '-' if config.arrows && preceding.ends_with('<') => replace(1, "←"),
+        '.' if config.ellipsis && preceding.ends_with("..") => replace(2, "…"),
+        '>' if config.arrows && preceding.ends_with('-') => replace(1, "→"),
+        '>' if config.arrows && preceding.ends_with('=') => replace(1, "⇒"),
+        '=' if config.arrows && preceding.ends_with('<') => replace(1, "⇐"),
+        '"' if config.quotes && opens_quote(preceding) => replace(0, "“"),
+        '"' if config.quotes => replace(0, "”"),
+        '\'' if config.quotes && opens_quote(preceding) => replace(0, "‘"),
+        //closing quotes and apostrophes are the same character
+        '\'' if config.quotes => replace(0, "’"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::SmartPunctuationConfig;
+    use crate::typography::{smart_punctuation, Replacement};
+
+    fn convert(preceding: &str, next: char) -> Option<(usize, &'static str)> {
+        smart_punctuation(&SmartPunctuationConfig::default(), preceding, next, |_| {
+            false
+        })
+        .map(|Replacement { delete, text }| (delete, text))
+    }
+
+    #[test]
+    fn replacements() {
+        assert_eq!(convert("wait-", '-'), Some((1, "—")));
+        assert_eq!(convert("wait", '-'), None);
+        assert_eq!(convert("so..", '.'), Some((2, "…")));
+        assert_eq!(convert("so.", '.'), None);
+        assert_eq!(convert("a-", '>'), Some((1, "→")));
+        assert_eq!(convert("a<", '='), Some((1, "⇐")));
+        assert_eq!(convert("", '"'), Some((0, "“")));
+        assert_eq!(convert("(", '"'), Some((0, "“")));
+        assert_eq!(convert("word", '"'), Some((0, "”")));
+        assert_eq!(convert("don", '\''), Some((0, "’")));
+        assert_eq!(convert("", '\''), Some((0, "‘")));
+    }
+
+    #[test]
+    fn disabled_rules() {
+        let config = SmartPunctuationConfig {
+            dashes: false,
+            quotes: false,
+            ..SmartPunctuationConfig::default()
+        };
+        assert_eq!(smart_punctuation(&config, "-", '-', |_| false), None);
+        assert_eq!(smart_punctuation(&config, "", '"', |_| false), None);
+        assert!(smart_punctuation(&config, "..", '.', |_| false).is_some());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::paths::config_dir;
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Words added by the user, one per line with an optional tab-separated count
+pub static USER_WORDS_FILE: &str = "words.tsv";
+/// Words the user never wants suggested, one per line
+pub static HIDDEN_WORDS_FILE: &str = "hidden_words.txt";
+/// Words whose autocorrection the user has reverted, which are never autocorrected again
+pub static AUTOCORRECT_EXCEPTIONS_FILE: &str = "autocorrect_exceptions.txt";
+
+fn config_file(filename: &str) -> io::Result<PathBuf> {
+    let dir = config_dir().map_err(|err| io::Error::new(io::ErrorKind::NotFound, err))?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(filename))
+}
+
+/// Appends a line to a user file, starting a new line first if the file doesn't end with one
+fn append_line(filename: &str, line: &str) -> io::Result<()> {
+    let path = config_file(filename)?;
+    let needs_newline = std::fs::read(&path)
+        .map(|contents| !contents.is_empty() && !contents.ends_with(b"\n"))
+        .unwrap_or(false);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    if needs_newline {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", line)
+}
+
+/// Removes every line that is just `word` from a user file
+fn remove_line(filename: &str, word: &str) -> io::Result<()> {
+    let path = config_file(filename)?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    let kept: Vec<&str> = contents
+        .lines()
+        .filter(|line| line.trim().to_lowercase() != word)
+        .collect();
+    if kept.len() != contents.lines().count() {
+        std::fs::write(&path, kept.join("\n") + "\n")?;
+    }
+    Ok(())
+}
+
+/// Whether a user word list already has `word`, ignoring any count after it
+fn has_word(filename: &str, word: &str) -> io::Result<bool> {
+    match std::fs::read_to_string(config_file(filename)?) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .any(|line| line.split('\t').next().unwrap_or("").trim().to_lowercase() == word)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Adds a word to the user's word list so it is suggested from then on, even if it was hidden
+pub fn add_word(word: &str) -> io::Result<()> {
+    let word = word.to_lowercase();
+    remove_line(HIDDEN_WORDS_FILE, &word)?;
+    if has_word(USER_WORDS_FILE, &word)? {
+        return Ok(());
+    }
+    append_line(USER_WORDS_FILE, &word)
+}
+
+/// Stops a word from being suggested again
+pub fn hide_word(word: &str) -> io::Result<()> {
+    append_line(HIDDEN_WORDS_FILE, &word.to_lowercase())
+}
+
+/// Stops a word from being autocorrected again
+pub fn add_autocorrect_exception(word: &str) -> io::Result<()> {
+    append_line(AUTOCORRECT_EXCEPTIONS_FILE, &word.to_lowercase())
+}
+
+/// Parses a file with a word on each line, like the hidden words
+pub fn parse_word_set(contents: &str) -> HashSet<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
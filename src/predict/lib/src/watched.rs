@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a file's modification time is trusted before it's checked again, so that every
+/// keystroke doesn't stat every user file
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A user file that is parsed on first use and parsed again whenever its modification time
+/// changes, so edits take effect without restarting the engine. A missing file parses as empty.
+pub struct WatchedFile<T> {
+    path: Option<PathBuf>,
+    parse: fn(&str) -> T,
+    state: Mutex<Option<WatchedState<T>>>,
+}
+
+struct WatchedState<T> {
+    modified: Option<SystemTime>,
+    /// When the modification time was last checked, or None to check it on the next use
+    checked: Option<Instant>,
+    value: Arc<T>,
+}
+
+impl<T: Default> WatchedFile<T> {
+    pub fn new(path: Option<PathBuf>, parse: fn(&str) -> T) -> WatchedFile<T> {
+        WatchedFile {
+            path,
+            parse,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// The current contents, reloading the file first if it has changed on disk. Changes are
+    /// noticed within `CHECK_INTERVAL`, or on the next use after `invalidate`.
+    pub fn get(&self) -> Arc<T> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Arc::new(T::default()),
+        };
+
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(current) = state.as_ref() {
+            if current
+                .checked
+                .is_some_and(|checked| checked.elapsed() < CHECK_INTERVAL)
+            {
+                return current.value.clone();
+            }
+        }
+
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        match state.as_mut() {
+            Some(current) if current.modified == modified => {
+                current.checked = Some(Instant::now());
+                current.value.clone()
+            }
+            _ => {
+                let value = Arc::new(match std::fs::read_to_string(path) {
+                    Ok(contents) => {
+                        log::info!("Loaded {:?}", path);
+                        (self.parse)(&contents)
+                    }
+                    Err(err) => {
+                        log::info!("Nothing loaded from {:?}: {}", path, err);
+                        T::default()
+                    }
+                });
+                *state = Some(WatchedState {
+                    modified,
+                    checked: Some(Instant::now()),
+                    value: value.clone(),
+                });
+                value
+            }
+        }
+    }
+}
+
+impl<T> WatchedFile<T> {
+    /// Makes the next use check the file again, for after the engine has changed it itself
+    pub fn invalidate(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(current) = state.as_mut() {
+            current.checked = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watched::WatchedFile;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    fn line_count(contents: &str) -> usize {
+        contents.lines().count()
+    }
+
+    #[test]
+    fn reloads_on_change() {
+        let path = std::env::temp_dir().join(format!("eei_watched_{}", std::process::id()));
+        let watched = WatchedFile::new(Some(path.clone()), line_count);
+        assert_eq!(*watched.get(), 0);
+
+        std::fs::write(&path, "a\nb\n").unwrap();
+        //the file was only just checked, so the change isn't noticed yet
+        assert_eq!(*watched.get(), 0);
+        watched.invalidate();
+        assert_eq!(*watched.get(), 2);
+
+        std::fs::write(&path, "a\nb\nc\n").unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        watched.invalidate();
+        assert_eq!(*watched.get(), 3);
+
+        std::fs::remove_file(&path).unwrap();
+        watched.invalidate();
+        assert_eq!(*watched.get(), 0);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+static CORPUS_EXTENSIONS: [&str; 3] = ["txt", "md", "markdown"];
+
+/// Text and markdown files in a directory and its subdirectories, sorted so that the generated
+/// data is deterministic. Symlinked directories aren't followed, so a link cycle can't loop
+/// forever.
+pub fn corpus_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let path = entry.path();
+            if file_type.is_symlink() && path.is_dir() {
+                continue;
+            } else if file_type.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(|extension| CORPUS_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+                .unwrap_or(false)
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Lowercased words in a text. A word is a run of letters, possibly with apostrophes between
+/// them. URLs and fenced markdown code blocks are skipped.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut in_code_block = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        } else if in_code_block {
+            continue;
+        }
+
+        for chunk in line.split_whitespace() {
+            if chunk.contains("://") || chunk.starts_with("www.") {
+                continue;
+            }
+            let chunk = chunk.replace('’', "'");
+            words.extend(
+                chunk
+                    .split(|c: char| !c.is_alphabetic() && c != '\'')
+                    .map(|word| word.trim_matches('\''))
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_lowercase),
+            );
+        }
+    }
+    words
+}
+
+/// Adds the words in a text to a count of each word
+pub fn count_words(text: &str, counts: &mut BTreeMap<String, u64>) {
+    for word in tokenize(text) {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::corpus::{corpus_files, tokenize};
+
+    #[test]
+    fn markdown() {
+        let text = "# Deploy notes\nDon’t restart the 'kubelet' (see https://example.com/x).\n\
+                    ```\nlet ignored = 1;\n```\nRe-run CI";
+        assert_eq!(
+            tokenize(text),
+            vec!["deploy", "notes", "don't", "restart", "the", "kubelet", "see", "re", "run", "ci"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycle() {
+        let dir = std::env::temp_dir().join(format!("eei_corpus_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/notes.md"), "notes").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("docs/loop")).unwrap();
+        let files = corpus_files(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files.unwrap(), vec![dir.join("docs/notes.md")]);
+    }
+}
@@ -0,0 +1,503 @@
+use encoding_rs::Encoding;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlagType {
+    /// One character per flag, the default
+    Char,
+    /// Two characters per flag
+    Long,
+    /// Comma separated numbers
+    Num,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HunspellError {
+    /// "affix file" or "dictionary file"
+    pub file: &'static str,
+    /// 1-based line number, if the error is about a particular line
+    pub line: Option<usize>,
+    pub reason: String,
+}
+
+impl Display for HunspellError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} line {}: {}", self.file, line, self.reason),
+            None => write!(f, "{}: {}", self.file, self.reason),
+        }
+    }
+}
+
+impl error::Error for HunspellError {}
+
+pub static AFFIX_FILE: &str = "affix file";
+static DICTIONARY_FILE: &str = "dictionary file";
+
+#[derive(Debug, Clone, PartialEq)]
+enum ConditionUnit {
+    Any,
+    Char(char),
+    Set { chars: Vec<char>, negated: bool },
+}
+
+impl ConditionUnit {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ConditionUnit::Any => true,
+            ConditionUnit::Char(expected) => c == *expected,
+            ConditionUnit::Set { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+fn parse_condition(condition: &str) -> Result<Vec<ConditionUnit>, String> {
+    let mut units = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        units.push(match c {
+            '.' => ConditionUnit::Any,
+            '[' => {
+                let mut set: Vec<char> = Vec::new();
+                let mut negated = false;
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some('^') if set.is_empty() && !negated => negated = true,
+                        Some(c) => set.push(c),
+                        None => return Err(format!("unterminated [ in condition {}", condition)),
+                    }
+                }
+                ConditionUnit::Set {
+                    chars: set,
+                    negated,
+                }
+            }
+            c => ConditionUnit::Char(c),
+        });
+    }
+    Ok(units)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AffixRule {
+    strip: String,
+    add: String,
+    condition: Vec<ConditionUnit>,
+}
+
+impl AffixRule {
+    /// Conditions are checked against the word the affix is added to, before stripping
+    fn condition_matches<'a>(&self, mut chars: impl Iterator<Item = &'a char>) -> bool {
+        self.condition
+            .iter()
+            .all(|unit| chars.next().map(|c| unit.matches(*c)).unwrap_or(false))
+    }
+
+    fn apply_suffix(&self, word: &[char]) -> Option<String> {
+        let strip: Vec<char> = self.strip.chars().collect();
+        if word.len() <= strip.len()
+            || !word.ends_with(&strip)
+            || !self.condition_matches(word.iter().rev().take(self.condition.len()).rev())
+        {
+            return None;
+        }
+        let stem: String = word[..word.len() - strip.len()].iter().collect();
+        Some(stem + &self.add)
+    }
+
+    fn apply_prefix(&self, word: &[char]) -> Option<String> {
+        let strip: Vec<char> = self.strip.chars().collect();
+        if word.len() <= strip.len()
+            || !word.starts_with(&strip)
+            || !self.condition_matches(word.iter())
+        {
+            return None;
+        }
+        let stem: String = word[strip.len()..].iter().collect();
+        Some(self.add.clone() + &stem)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AffixClass {
+    cross_product: bool,
+    rules: Vec<AffixRule>,
+}
+
+/// The prefix and suffix rules of an `.aff` file
+#[derive(Debug)]
+pub struct Affixes {
+    flag_type: FlagType,
+    /// Flag sets from `AF` lines, which `.dic` entries can refer to by number
+    flag_aliases: Vec<Vec<String>>,
+    prefixes: HashMap<String, AffixClass>,
+    suffixes: HashMap<String, AffixClass>,
+    /// `FORBIDDENWORD`: the entry isn't a word, even if another entry's affixes produce it
+    forbidden_flag: Option<String>,
+    /// `NEEDAFFIX`: only the affixed forms of the entry are words, not the root itself
+    need_affix_flag: Option<String>,
+    /// `ONLYINCOMPOUND`: the entry only appears inside compounds, which aren't expanded
+    only_in_compound_flag: Option<String>,
+}
+
+impl Affixes {
+    pub fn parse(contents: &str) -> Result<Affixes, HunspellError> {
+        let mut affixes = Affixes {
+            flag_type: FlagType::Char,
+            flag_aliases: Vec::new(),
+            prefixes: HashMap::new(),
+            suffixes: HashMap::new(),
+            forbidden_flag: None,
+            need_affix_flag: None,
+            only_in_compound_flag: None,
+        };
+        let mut alias_count_seen = false;
+
+        for (idx, line) in contents.lines().enumerate() {
+            let error = |reason: String| HunspellError {
+                file: AFFIX_FILE,
+                line: Some(idx + 1),
+                reason,
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", flag_type, ..] => {
+                    affixes.flag_type = match *flag_type {
+                        "long" => FlagType::Long,
+                        "num" => FlagType::Num,
+                        //flags are already read as characters rather than bytes
+                        "UTF-8" => FlagType::Char,
+                        other => return Err(error(format!("unknown flag type {}", other))),
+                    }
+                }
+                ["FORBIDDENWORD", flag, ..] => affixes.forbidden_flag = Some(flag.to_string()),
+                //PSEUDOROOT is the old name of NEEDAFFIX
+                ["NEEDAFFIX" | "PSEUDOROOT", flag, ..] => {
+                    affixes.need_affix_flag = Some(flag.to_string())
+                }
+                ["ONLYINCOMPOUND", flag, ..] => {
+                    affixes.only_in_compound_flag = Some(flag.to_string())
+                }
+                ["AF", flags, ..] => {
+                    //the first AF line is the number of aliases
+                    if alias_count_seen {
+                        let flags = affixes.split_flags(flags);
+                        affixes.flag_aliases.push(flags);
+                    } else {
+                        alias_count_seen = true;
+                    }
+                }
+                [kind @ ("PFX" | "SFX"), flag, fields @ ..] => {
+                    let flag = flag.to_string();
+                    let classes = if *kind == "PFX" {
+                        &mut affixes.prefixes
+                    } else {
+                        &mut affixes.suffixes
+                    };
+                    match classes.get_mut(&flag) {
+                        //the first line for a flag says whether it allows cross products
+                        None => {
+                            let cross_product = match fields.first() {
+                                Some(&"Y") => true,
+                                Some(&"N") => false,
+                                _ => return Err(error(format!("invalid {} header", kind))),
+                            };
+                            classes.insert(
+                                flag,
+                                AffixClass {
+                                    cross_product,
+                                    rules: Vec::new(),
+                                },
+                            );
+                        }
+                        Some(class) => {
+                            let (strip, add) = match fields {
+                                [strip, add, ..] => (strip, add),
+                                _ => return Err(error(format!("invalid {} rule", kind))),
+                            };
+                            let zero_as_empty = |value: &str| {
+                                if value == "0" {
+                                    String::new()
+                                } else {
+                                    value.to_string()
+                                }
+                            };
+                            //continuation flags after a slash aren't used by unmunch either
+                            let add = add.split('/').next().unwrap_or("");
+                            class.rules.push(AffixRule {
+                                strip: zero_as_empty(strip),
+                                add: zero_as_empty(add),
+                                condition: parse_condition(fields.get(2).unwrap_or(&"."))
+                                    .map_err(error)?,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(affixes)
+    }
+
+    fn split_flags(&self, flags: &str) -> Vec<String> {
+        match self.flag_type {
+            FlagType::Char => flags.chars().map(String::from).collect(),
+            FlagType::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars.chunks(2).map(|flag| flag.iter().collect()).collect()
+            }
+            FlagType::Num => flags
+                .split(',')
+                .map(|flag| flag.trim().to_string())
+                .collect(),
+        }
+    }
+
+    /// The flags of a `.dic` entry, which are a number if the affix file has flag aliases
+    fn entry_flags(&self, flags: &str) -> Result<Vec<String>, String> {
+        if self.flag_aliases.is_empty() {
+            return Ok(self.split_flags(flags));
+        }
+        flags
+            .parse::<usize>()
+            .ok()
+            .and_then(|alias| alias.checked_sub(1))
+            .and_then(|idx| self.flag_aliases.get(idx))
+            .cloned()
+            .ok_or_else(|| format!("unknown flag alias {}", flags))
+    }
+
+    fn has_flag(flags: &[String], flag: &Option<String>) -> bool {
+        flag.as_ref()
+            .map(|flag| flags.contains(flag))
+            .unwrap_or(false)
+    }
+
+    /// Adds a word and every form its flags produce to `words`
+    fn expand_word(&self, word: &str, flags: &[String], words: &mut Vec<String>) {
+        if !Affixes::has_flag(flags, &self.need_affix_flag) {
+            words.push(word.to_string());
+        }
+        let chars: Vec<char> = word.chars().collect();
+
+        let mut cross_suffixed: Vec<Vec<char>> = Vec::new();
+        for class in flags.iter().filter_map(|flag| self.suffixes.get(flag)) {
+            for rule in class.rules.iter() {
+                if let Some(suffixed) = rule.apply_suffix(&chars) {
+                    if class.cross_product {
+                        cross_suffixed.push(suffixed.chars().collect());
+                    }
+                    words.push(suffixed);
+                }
+            }
+        }
+
+        for class in flags.iter().filter_map(|flag| self.prefixes.get(flag)) {
+            for rule in class.rules.iter() {
+                words.extend(rule.apply_prefix(&chars));
+                if class.cross_product {
+                    for suffixed in cross_suffixed.iter() {
+                        words.extend(rule.apply_prefix(suffixed));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Every word produced by the entries of a `.dic` file, in the order of the entries
+    pub fn expand(&self, dic: &str) -> Result<Vec<String>, HunspellError> {
+        let mut words = Vec::new();
+        let mut forbidden = HashSet::new();
+        for (idx, line) in dic.lines().enumerate() {
+            //the first line is the number of entries
+            if idx == 0 && line.trim().parse::<usize>().is_ok() {
+                continue;
+            }
+            let entry = match dic_entry(line) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let (word, flags) = split_word_flags(entry);
+            let flags = match flags {
+                Some(flags) => self.entry_flags(flags).map_err(|reason| HunspellError {
+                    file: DICTIONARY_FILE,
+                    line: Some(idx + 1),
+                    reason,
+                })?,
+                None => Vec::new(),
+            };
+            if Affixes::has_flag(&flags, &self.forbidden_flag) {
+                forbidden.insert(word);
+            } else if !Affixes::has_flag(&flags, &self.only_in_compound_flag) {
+                self.expand_word(&word, &flags, &mut words);
+            }
+        }
+        words.retain(|word| !forbidden.contains(word));
+        Ok(words)
+    }
+}
+
+/// The word and flags of a `.dic` line, without any morphological fields after them
+fn dic_entry(line: &str) -> Option<&str> {
+    let entry = line.split('\t').next().unwrap_or("");
+    //morphological fields like "po:noun" can also be separated by spaces
+    let end = entry
+        .match_indices(' ')
+        .map(|(idx, _)| idx)
+        .find(|idx| entry[idx + 1..].chars().nth(2) == Some(':'))
+        .unwrap_or(entry.len());
+    let entry = entry[..end].trim();
+    if entry.is_empty() || entry.starts_with('#') {
+        None
+    } else {
+        Some(entry)
+    }
+}
+
+/// Splits `word/flags`, where a slash in the word itself is escaped as `\/`
+fn split_word_flags(entry: &str) -> (String, Option<&str>) {
+    let mut escaped = false;
+    for (idx, c) in entry.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            '/' if !escaped && idx > 0 => {
+                return (entry[..idx].replace("\\/", "/"), Some(&entry[idx + 1..]))
+            }
+            _ => escaped = false,
+        }
+    }
+    (entry.replace("\\/", "/"), None)
+}
+
+/// Decodes a dictionary file with the character set named by the affix file's `SET` line,
+/// which is ISO8859-1 if there isn't one
+fn decode(bytes: &[u8], encoding: &str, file: &'static str) -> Result<String, HunspellError> {
+    match encoding {
+        "UTF-8" => String::from_utf8(bytes.to_vec())
+            .map(|contents| contents.trim_start_matches('\u{feff}').to_string())
+            .map_err(|err| HunspellError {
+                file,
+                line: None,
+                reason: err.to_string(),
+            }),
+        "ISO8859-1" => Ok(bytes.iter().map(|byte| char::from(*byte)).collect()),
+        other => {
+            let error = |reason| HunspellError {
+                file,
+                line: None,
+                reason,
+            };
+            //a few of hunspell's names differ from the standard labels
+            let label = match other {
+                "microsoft-cp1251" => "windows-1251",
+                "TIS620-2533" => "tis-620",
+                label => label,
+            };
+            let charset = Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| error(format!("unsupported character set {}", other)))?;
+            charset
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(|contents| contents.into_owned())
+                .ok_or_else(|| error(format!("invalid {} text", other)))
+        }
+    }
+}
+
+/// Every word described by the contents of a `.dic` file and its `.aff` file, like hunspell's
+/// `unmunch`: each root word, the root with each of its prefixes and suffixes, and the root with
+/// both a prefix and a suffix when both allow cross products. Forbidden words, roots that need an
+/// affix and compound-only entries are left out.
+pub fn expand_dictionary(dic: &[u8], aff: &[u8]) -> Result<Vec<String>, HunspellError> {
+    //SET is plain ASCII, so it can be found before knowing the encoding
+    let encoding = String::from_utf8_lossy(aff)
+        .lines()
+        .find_map(|line| {
+            line.trim()
+                .strip_prefix("SET ")
+                .map(|set| set.trim().to_string())
+        })
+        .unwrap_or_else(|| "ISO8859-1".to_string());
+
+    let affixes = Affixes::parse(&decode(aff, &encoding, AFFIX_FILE)?)?;
+    affixes.expand(&decode(dic, &encoding, DICTIONARY_FILE)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hunspell::expand_dictionary;
+
+    static AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwzESIANRTOLCDUGMPHBYFVKWZ'
+
+PFX A Y 1
+PFX A   0     re         .
+
+PFX I Y 1
+PFX I   0     in         .
+
+SFX D Y 4
+SFX D   0     d          e
+SFX D   y     ied        [^aeiou]y
+SFX D   0     ed         [^ey]
+SFX D   0     ed         [aeiou]y
+
+SFX M Y 1
+SFX M   0     's         .
+
+SFX N N 1
+SFX N   e     ion        e
+";
+
+    #[test]
+    fn expansion() {
+        let dic = "4\ncreate/ADN\ncopy/D\nname/M po:noun\nand\\/or/M\n";
+        let words = expand_dictionary(dic.as_bytes(), AFF.as_bytes()).unwrap();
+        assert_eq!(
+            words,
+            vec![
+                "create",
+                "created",
+                "creation",
+                "recreate",
+                "recreated",
+                "copy",
+                "copied",
+                "name",
+                "name's",
+                "and/or",
+                "and/or's",
+            ]
+        );
+    }
+
+    #[test]
+    fn flag_types() {
+        let aff = "FLAG long\nAF 2\nAF AaBb\nAF Bb\nSFX Aa Y 1\nSFX Aa 0 s .\n\
+                   PFX Bb Y 1\nPFX Bb 0 un .\n";
+        let words = expand_dictionary(b"2\ndo/1\ntie/2\n", aff.as_bytes()).unwrap();
+        assert_eq!(words, vec!["do", "dos", "undo", "undos", "tie", "untie"]);
+
+        let latin1 = expand_dictionary(b"1\ncaf\xe9/1\n", b"FLAG num\nSFX 1 N 1\nSFX 1 0 s .\n");
+        assert_eq!(latin1.unwrap(), vec!["café", "cafés"]);
+
+        //"мир" and the suffix "ы" in KOI8-R
+        let koi8 = expand_dictionary(
+            b"1\n\xcd\xc9\xd2/A\n",
+            b"SET KOI8-R\nSFX A N 1\nSFX A 0 \xd9 .\n",
+        );
+        assert_eq!(koi8.unwrap(), vec!["мир", "миры"]);
+        let unknown = expand_dictionary(b"1\nword\n", b"SET ISCII-DEVANAGARI\n");
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn special_flags() {
+        let aff = format!("{}FORBIDDENWORD !\nNEEDAFFIX X\nONLYINCOMPOUND C\n", AFF);
+        let dic = "5\nname/MX\ncopy/D\ncopied/!\nconnect/DC\nfoo/!\n";
+        let words = expand_dictionary(dic.as_bytes(), aff.as_bytes()).unwrap();
+        assert_eq!(words, vec!["name's", "copy"]);
+    }
+}
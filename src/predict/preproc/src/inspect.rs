@@ -0,0 +1,559 @@
+use common::bundle::{
+    dictionary_bundle, Bundle, BundleMetadata, COMMANDS_SECTION, DICTIONARY_SECTION,
+    LATEX_COMMANDS_BUNDLE, NAMES_SECTION, SHORTCODES_SECTION, SYMBOLS_BUNDLE, SYMBOLS_SECTION,
+    UNICODE_NAMES_BUNDLE,
+};
+use common::symbols::{key_shortcode, SymbolData, SymbolInfo, KEYWORD_SEPARATOR};
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Map, Streamer};
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::error;
+use std::fmt::Display;
+use std::path::Path;
+
+static USAGE: &str = "Usage: preproc inspect <command> [--data-dir <dir>] [--locale <locale>]
+  words [prefix] [--regex <pattern>]       dictionary words and their frequencies
+  shortcodes [prefix] [--regex <pattern>]  shortcode keys and their symbols
+  freq <word>                              a word's frequency
+  symbol <symbol>                          a symbol's shortcodes and search keys
+  stats                                    summary statistics, including any installed
+                                           unicode names and LaTeX commands
+  diff <dir> <other dir>                   differences between two sets of data files";
+
+/// The contents of a directory's dictionary and symbol bundles
+struct DataSet {
+    dictionary_name: String,
+    dictionary: Map<Vec<u8>>,
+    dictionary_metadata: BundleMetadata,
+    shortcodes: Map<Vec<u8>>,
+    symbols: SymbolData,
+    symbols_metadata: BundleMetadata,
+}
+
+impl DataSet {
+    fn load(dir: &Path, locale: &str) -> Result<DataSet, Box<dyn error::Error>> {
+        let read = |name: &str| {
+            let path = dir.join(name);
+            std::fs::read(&path)
+                .map_err(|err| format!("Failed to read {}: {}", path.display(), err))
+        };
+
+        let dictionary_name = dictionary_bundle(locale);
+        DataSet::parse(
+            dictionary_name.clone(),
+            &read(&dictionary_name)?,
+            &read(SYMBOLS_BUNDLE)?,
+        )
+    }
+
+    fn parse(
+        dictionary_name: String,
+        dictionary_bytes: &[u8],
+        symbols_bytes: &[u8],
+    ) -> Result<DataSet, Box<dyn error::Error>> {
+        let dictionary_bundle = Bundle::parse(dictionary_bytes)
+            .map_err(|err| format!("{}: {}", dictionary_name, err))?;
+        let symbols_bundle =
+            Bundle::parse(symbols_bytes).map_err(|err| format!("{}: {}", SYMBOLS_BUNDLE, err))?;
+
+        Ok(DataSet {
+            dictionary_name,
+            dictionary: Map::new(dictionary_bundle.section(DICTIONARY_SECTION)?.to_vec())?,
+            dictionary_metadata: dictionary_bundle.metadata,
+            shortcodes: Map::new(symbols_bundle.section(SHORTCODES_SECTION)?.to_vec())?,
+            symbols: bincode::deserialize(symbols_bundle.section(SYMBOLS_SECTION)?)?,
+            symbols_metadata: symbols_bundle.metadata,
+        })
+    }
+
+    /// The symbols listed for a value of the shortcode FST, or `?` for invalid ids
+    fn key_symbols(&self, value: u64) -> Vec<&str> {
+        self.symbols
+            .key_symbols
+            .get(value as usize)
+            .map(|ids| {
+                ids.iter()
+                    .map(|id| {
+                        self.symbols
+                            .symbols
+                            .get(*id as usize)
+                            .map(|info| info.symbol.as_str())
+                            .unwrap_or("?")
+                    })
+                    .collect()
+            })
+            .unwrap_or_else(|| vec!["?"])
+    }
+
+    fn describe_key_symbols(&self, value: u64) -> String {
+        self.key_symbols(value).join(" ")
+    }
+
+    fn symbol_by_name(&self) -> BTreeMap<&str, &SymbolInfo> {
+        self.symbols
+            .symbols
+            .iter()
+            .map(|info| (info.symbol.as_str(), info))
+            .collect()
+    }
+}
+
+/// How `words` and `shortcodes` select entries. Keyword keys are matched by their keyword.
+enum Pattern {
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn matching(&self, map: &Map<Vec<u8>>) -> Vec<(String, u64)> {
+        let mut matches = Vec::new();
+        let mut collect = |key: &[u8], value: u64| {
+            let key = String::from_utf8_lossy(key).into_owned();
+            let is_match = match self {
+                Pattern::Prefix(_) => true,
+                Pattern::Regex(regex) => regex.is_match(key_name(&key)),
+            };
+            if is_match {
+                matches.push((key, value));
+            }
+        };
+
+        match self {
+            Pattern::Prefix(prefix) => {
+                let mut stream = map.search(Str::new(prefix).starts_with()).into_stream();
+                while let Some((key, value)) = stream.next() {
+                    collect(key, value);
+                }
+            }
+            Pattern::Regex(_) => {
+                let mut stream = map.stream();
+                while let Some((key, value)) = stream.next() {
+                    collect(key, value);
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// The part of a key that is searched for, which is the keyword for keyword keys
+fn key_name(key: &str) -> &str {
+    key.split(KEYWORD_SEPARATOR).next().unwrap_or(key)
+}
+
+/// A key as it's shown to people, since keyword keys contain a separator
+fn display_key(key: &str) -> String {
+    if key_shortcode(key) == key {
+        key.to_string()
+    } else {
+        format!("{} (keyword of {})", key_name(key), key_shortcode(key))
+    }
+}
+
+fn print_sources(metadata: &BundleMetadata) {
+    for source in metadata.sources.iter() {
+        println!("  source {} (crc32 {:08x})", source.name, source.crc32);
+    }
+}
+
+fn stats(data: &DataSet) {
+    let mut words = 0;
+    let mut zero_frequency = 0;
+    let mut stream = data.dictionary.stream();
+    while let Some((_word, freq)) = stream.next() {
+        words += 1;
+        if freq == 0 {
+            zero_frequency += 1;
+        }
+    }
+    println!("{}", data.dictionary_name);
+    print_sources(&data.dictionary_metadata);
+    println!("  {} words, {} without frequency", words, zero_frequency);
+
+    let mut shortcodes = 0;
+    let mut keyword_keys = 0;
+    let mut shared_shortcodes = 0;
+    let mut used_symbols: HashSet<u32> = HashSet::new();
+    let mut stream = data.shortcodes.stream();
+    while let Some((key, value)) = stream.next() {
+        let key = String::from_utf8_lossy(key);
+        if key_shortcode(&key) == key {
+            shortcodes += 1;
+            if data.key_symbols(value).len() > 1 {
+                shared_shortcodes += 1;
+            }
+        } else {
+            keyword_keys += 1;
+        }
+        if let Some(ids) = data.symbols.key_symbols.get(value as usize) {
+            used_symbols.extend(ids.iter().copied());
+        }
+    }
+    println!("{}", SYMBOLS_BUNDLE);
+    print_sources(&data.symbols_metadata);
+    println!(
+        "  {} shortcodes ({} belonging to several symbols), {} keyword keys",
+        shortcodes, shared_shortcodes, keyword_keys
+    );
+    println!(
+        "  {} symbols, {} distinct symbol lists",
+        data.symbols.symbols.len(),
+        data.symbols.key_symbols.len()
+    );
+
+    //symbols no key leads to can never be found in the symbol table
+    let orphans: Vec<&SymbolInfo> = data
+        .symbols
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(id, _info)| !used_symbols.contains(&(*id as u32)))
+        .map(|(_id, info)| info)
+        .collect();
+    println!("  {} orphan symbols", orphans.len());
+    for info in orphans {
+        println!("    {}\t{}", info.symbol, info.shortcodes.join(", "));
+    }
+}
+
+/// Statistics for the optional bundles that are installed separately, if they're in `dir`. Parsing
+/// them checks that they're intact.
+fn optional_stats(dir: &Path) -> Result<(), Box<dyn error::Error>> {
+    for (filename, section, kind) in [
+        (UNICODE_NAMES_BUNDLE, NAMES_SECTION, "name keys"),
+        (LATEX_COMMANDS_BUNDLE, COMMANDS_SECTION, "commands"),
+    ] {
+        let bytes = match std::fs::read(dir.join(filename)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("Could not read {}: {}", filename, err).into()),
+        };
+        let error = |err: &dyn Display| format!("{}: {}", filename, err);
+        let bundle = Bundle::parse(&bytes).map_err(|err| error(&err))?;
+        let keys = Map::new(bundle.section(section).map_err(|err| error(&err))?)
+            .map_err(|err| error(&err))?;
+        println!("{}", filename);
+        print_sources(&bundle.metadata);
+        println!("  {} {}", keys.len(), kind);
+    }
+    Ok(())
+}
+
+fn show_symbol(data: &DataSet, symbol: &str) -> Result<(), Box<dyn error::Error>> {
+    let (id, info) = data
+        .symbols
+        .symbols
+        .iter()
+        .enumerate()
+        .find(|(_id, info)| info.symbol == symbol)
+        .ok_or_else(|| format!("{} is not in {}", symbol, SYMBOLS_BUNDLE))?;
+
+    println!("{} (id {})", info.symbol, id);
+    if let Some(category) = &info.category {
+        println!("  category: {}", category);
+    }
+    if let Some(description) = &info.description {
+        println!("  description: {}", description);
+    }
+    println!("  shortcodes: {}", info.shortcodes.join(", "));
+
+    let mut stream = data.shortcodes.stream();
+    while let Some((key, value)) = stream.next() {
+        let listed = data
+            .symbols
+            .key_symbols
+            .get(value as usize)
+            .and_then(|ids| ids.iter().position(|other| *other as usize == id));
+        if let Some(rank) = listed {
+            println!(
+                "  key {} (rank {})",
+                display_key(&String::from_utf8_lossy(key)),
+                rank + 1
+            );
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default, Debug, PartialEq)]
+struct DiffCounts {
+    added: usize,
+    removed: usize,
+    changed: usize,
+}
+
+impl DiffCounts {
+    fn summary(&self, what: &str) -> String {
+        format!(
+            "{}: {} added, {} removed, {} changed",
+            what, self.added, self.removed, self.changed
+        )
+    }
+}
+
+/// Prints every key that was added, removed or changed between two maps. Values are compared by
+/// their descriptions, since the same value can mean different things in different data sets.
+fn diff_maps(
+    old: &Map<Vec<u8>>,
+    new: &Map<Vec<u8>>,
+    describe_old: impl Fn(u64) -> String,
+    describe_new: impl Fn(u64) -> String,
+) -> DiffCounts {
+    let mut counts = DiffCounts::default();
+    let mut stream = old.op().add(new).union();
+    while let Some((key, values)) = stream.next() {
+        let key = display_key(&String::from_utf8_lossy(key));
+        let old_value = values.iter().find(|value| value.index == 0);
+        let new_value = values.iter().find(|value| value.index == 1);
+        match (old_value, new_value) {
+            (Some(old_value), Some(new_value)) => {
+                let old_description = describe_old(old_value.value);
+                let new_description = describe_new(new_value.value);
+                if old_description != new_description {
+                    println!("~ {}\t{} -> {}", key, old_description, new_description);
+                    counts.changed += 1;
+                }
+            }
+            (Some(old_value), None) => {
+                println!("- {}\t{}", key, describe_old(old_value.value));
+                counts.removed += 1;
+            }
+            (None, Some(new_value)) => {
+                println!("+ {}\t{}", key, describe_new(new_value.value));
+                counts.added += 1;
+            }
+            (None, None) => {}
+        }
+    }
+    counts
+}
+
+fn describe_symbol(info: &SymbolInfo) -> String {
+    format!(
+        "[{}] {} {}",
+        info.shortcodes.join(", "),
+        info.category.as_deref().unwrap_or("-"),
+        info.description.as_deref().unwrap_or("-")
+    )
+}
+
+fn diff_symbols(old: &DataSet, new: &DataSet) -> DiffCounts {
+    let mut counts = DiffCounts::default();
+    let old_symbols = old.symbol_by_name();
+    let new_symbols = new.symbol_by_name();
+    for (symbol, info) in old_symbols.iter() {
+        match new_symbols.get(symbol) {
+            Some(new_info) if new_info != info => {
+                println!(
+                    "~ {}\t{} -> {}",
+                    symbol,
+                    describe_symbol(info),
+                    describe_symbol(new_info)
+                );
+                counts.changed += 1;
+            }
+            Some(_) => {}
+            None => {
+                println!("- {}\t{}", symbol, describe_symbol(info));
+                counts.removed += 1;
+            }
+        }
+    }
+    for (symbol, info) in new_symbols.iter() {
+        if !old_symbols.contains_key(symbol) {
+            println!("+ {}\t{}", symbol, describe_symbol(info));
+            counts.added += 1;
+        }
+    }
+    counts
+}
+
+/// Prints the differences between two data sets, returning the counts for words, shortcode keys
+/// and symbols
+fn diff(old: &DataSet, new: &DataSet) -> [DiffCounts; 3] {
+    println!("-- Dictionary --");
+    let words = diff_maps(
+        &old.dictionary,
+        &new.dictionary,
+        |freq| freq.to_string(),
+        |freq| freq.to_string(),
+    );
+    println!("-- Shortcodes --");
+    let shortcodes = diff_maps(
+        &old.shortcodes,
+        &new.shortcodes,
+        |value| old.describe_key_symbols(value),
+        |value| new.describe_key_symbols(value),
+    );
+    println!("-- Symbols --");
+    let symbols = diff_symbols(old, new);
+
+    println!("{}", words.summary("words"));
+    println!("{}", shortcodes.summary("shortcode keys"));
+    println!("{}", symbols.summary("symbols"));
+    [words, shortcodes, symbols]
+}
+
+fn usage() -> Box<dyn error::Error> {
+    eprintln!("{}", USAGE);
+    "Invalid arguments for inspect".into()
+}
+
+/// Runs `preproc inspect`, given the arguments after `inspect`
+pub fn inspect(
+    args: &[String],
+    data_dir: &str,
+    locale: &str,
+    regex: Option<&str>,
+) -> Result<(), Box<dyn error::Error>> {
+    let load = |dir: &str| DataSet::load(Path::new(dir), locale);
+    let arg = |idx: usize| args.get(idx).map(|arg| arg.as_str()).ok_or_else(usage);
+    let pattern = || -> Result<Pattern, Box<dyn error::Error>> {
+        Ok(match regex {
+            Some(regex) => Pattern::Regex(Regex::new(regex)?),
+            None => Pattern::Prefix(arg(1).unwrap_or("").to_string()),
+        })
+    };
+
+    match arg(0)? {
+        "words" => {
+            let data = load(data_dir)?;
+            for (word, freq) in pattern()?.matching(&data.dictionary) {
+                println!("{}\t{}", word, freq);
+            }
+        }
+        "shortcodes" => {
+            let data = load(data_dir)?;
+            for (key, value) in pattern()?.matching(&data.shortcodes) {
+                println!(
+                    "{}\t{}",
+                    display_key(&key),
+                    data.describe_key_symbols(value)
+                );
+            }
+        }
+        "freq" => {
+            let data = load(data_dir)?;
+            let word = arg(1)?;
+            let freq = data
+                .dictionary
+                .get(word)
+                .ok_or_else(|| format!("{} is not in {}", word, data.dictionary_name))?;
+            println!("{}\t{}", word, freq);
+        }
+        "symbol" => show_symbol(&load(data_dir)?, arg(1)?)?,
+        "stats" => {
+            stats(&load(data_dir)?);
+            optional_stats(Path::new(data_dir))?;
+        }
+        "diff" => {
+            let old = load(arg(1)?)?;
+            let new = load(arg(2)?)?;
+            diff(&old, &new);
+        }
+        _ => return Err(usage()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspect::{diff, DataSet, DiffCounts, Pattern};
+    use common::bundle::{write_bundle, DICTIONARY_SECTION, SHORTCODES_SECTION, SYMBOLS_SECTION};
+    use common::symbols::{keyword_key, SymbolData, SymbolInfo};
+    use fst::Map;
+    use regex::Regex;
+
+    fn map(entries: &[(&str, u64)]) -> Map<Vec<u8>> {
+        let mut entries = entries.to_vec();
+        entries.sort();
+        Map::from_iter(entries).unwrap()
+    }
+
+    fn symbol(symbol: &str, shortcode: &str) -> SymbolInfo {
+        SymbolInfo {
+            shortcodes: vec![shortcode.to_string()],
+            ..SymbolInfo::new(symbol.to_string())
+        }
+    }
+
+    fn data_set(words: &[(&str, u64)], shortcodes: &[(&str, u64)], symbols: SymbolData) -> DataSet {
+        let dictionary = map(words).into_fst().into_inner();
+        let shortcodes = map(shortcodes).into_fst().into_inner();
+        let symbols = bincode::serialize(&symbols).unwrap();
+        DataSet::parse(
+            "dictionary-xx_XX.bundle".to_string(),
+            &write_bundle(vec![], &[(DICTIONARY_SECTION, &dictionary)]),
+            &write_bundle(
+                vec![],
+                &[
+                    (SHORTCODES_SECTION, &shortcodes),
+                    (SYMBOLS_SECTION, &symbols),
+                ],
+            ),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn patterns() {
+        let heart_keyword = keyword_key("love", "heart");
+        let shortcodes = map(&[
+            ("heart", 0),
+            ("heavy_plus", 1),
+            (&heart_keyword, 0),
+            ("smile", 2),
+        ]);
+
+        let prefix: Vec<String> = Pattern::Prefix("hea".to_string())
+            .matching(&shortcodes)
+            .into_iter()
+            .map(|(key, _value)| key)
+            .collect();
+        assert_eq!(prefix, vec!["heart", "heavy_plus"]);
+
+        //keyword keys are matched by their keyword, not the shortcode they lead to
+        let regex = Pattern::Regex(Regex::new("^(lo|sm)").unwrap()).matching(&shortcodes);
+        assert_eq!(regex, vec![(heart_keyword, 0), ("smile".to_string(), 2)]);
+        assert!(Pattern::Regex(Regex::new("art$").unwrap())
+            .matching(&shortcodes)
+            .iter()
+            .all(|(key, _value)| key == "heart"));
+    }
+
+    #[test]
+    fn diffs() {
+        let old = data_set(
+            &[("cat", 10), ("dog", 5), ("emu", 1)],
+            &[("heart", 0), ("smile", 1)],
+            SymbolData {
+                symbols: vec![symbol("❤", "heart"), symbol("🙂", "smile")],
+                key_symbols: vec![vec![0], vec![1]],
+            },
+        );
+        //the same symbols in a different order, so only the values of the keys differ
+        let new = data_set(
+            &[("cat", 10), ("dog", 7), ("fox", 2)],
+            &[("heart", 1), ("smile", 0), ("star", 2)],
+            SymbolData {
+                symbols: vec![
+                    symbol("🙂", "smile"),
+                    symbol("❤", "heart"),
+                    symbol("⭐", "star"),
+                ],
+                key_symbols: vec![vec![0], vec![1], vec![2]],
+            },
+        );
+
+        let [words, shortcodes, symbols] = diff(&old, &new);
+        let counts = |added, removed, changed| DiffCounts {
+            added,
+            removed,
+            changed,
+        };
+        assert_eq!(words, counts(1, 1, 1));
+        assert_eq!(shortcodes, counts(1, 0, 0));
+        assert_eq!(symbols, counts(1, 0, 0));
+    }
+}
@@ -1,41 +1,59 @@
+use common::bundle::{
+    dictionary_bundle, pack_bundle, phrases_bundle, write_bundle, SourceInfo, COMMANDS_SECTION,
+    DEFAULT_LOCALE, DICTIONARY_SECTION, LATEX_COMMANDS_BUNDLE, NAMES_SECTION, SHORTCODES_SECTION,
+    SYMBOLS_BUNDLE, SYMBOLS_SECTION, UNICODE_NAMES_BUNDLE,
+};
+use common::symbols::{
+    key_shortcode, parse_shortcode_entries, ShortcodeEntry, ShortcodeParseError, SymbolData,
+    SymbolInfo,
+};
+use common::unicode::{name_word_starts, parse_unicode, UnicodeParseError, NAME_SEPARATOR};
+use common::words::{parse_word_counts, WordParseError};
 use fst::MapBuilder;
-use std::collections::{HashMap, HashSet};
+use hunspell::HunspellError;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io;
 use std::io::{BufRead, Write};
-use std::num::ParseIntError;
+use std::path::Path;
 use std::{env, error};
 
+mod corpus;
+mod hunspell;
+mod inspect;
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 enum InvalidParseError {
     Json(String),
-    Hex(ParseIntError),
-    Codepoint(u32),
+    Unicode(UnicodeParseError),
     WordFreq(String),
+    Latex(String),
+    Custom(ShortcodeParseError),
+    Hunspell(String, HunspellError),
+    Words(String, WordParseError),
 }
 
 //code point;class;char;entity name;entity set;note/description;CHARACTER NAME
 
 impl Display for InvalidParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            InvalidParseError::Custom(err) => write!(f, "custom_shortcodes.tsv {}", err),
+            InvalidParseError::Hunspell(path, err) => write!(f, "{}: {}", path, err),
+            InvalidParseError::Words(path, err) => write!(f, "{} {}", path, err),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
 impl error::Error for InvalidParseError {}
 
-//https://stackoverflow.com/questions/69152223/unicode-codepoint-to-rust-string
-fn parse_unicode(input: &str) -> Result<char, InvalidParseError> {
-    let unicode = u32::from_str_radix(input, 16).map_err(InvalidParseError::Hex)?;
-    char::from_u32(unicode).ok_or(InvalidParseError::Codepoint(unicode))
-}
-
 fn parse_github_emoji_url(url: &str) -> Result<String, InvalidParseError> {
     let bytecode_strings = url
         .split('/')
-        .last()
+        .next_back()
         .ok_or(InvalidParseError::Json(url.to_owned()))?
         .split('.')
         .next()
@@ -43,21 +61,130 @@ fn parse_github_emoji_url(url: &str) -> Result<String, InvalidParseError> {
         .split('-');
 
     bytecode_strings
-        .map(parse_unicode)
-        .collect::<Result<Vec<_>, _>>()
-        .map(|char_vec| char_vec.into_iter().collect::<String>())
+        .map(|bytecode| parse_unicode(bytecode).map_err(InvalidParseError::Unicode))
+        .collect::<Result<String, _>>()
+}
+
+static MATH_CLASS_URL: &str = "https://www.unicode.org/Public/math/revision-15/MathClassEx-15.txt";
+static GITHUB_EMOJI_URL: &str = "https://api.github.com/emojis";
+static DEFAULT_MATH_CLASS_FILE: &str = "MathClassEx-15.txt";
+static DEFAULT_EMOJI_FILE: &str = "emojis.json";
+static DEFAULT_FREQ_FILE: &str = "count_1w.txt";
+
+static DEFAULT_CORPUS_NAME: &str = "corpus";
+static DEFAULT_NGRAM_FILE: &str = "count_2w.txt";
+/// Phrases counted less often than this in the n-gram list are too rare to be worth suggesting
+const DEFAULT_PHRASE_MIN_COUNT: u64 = 1_000_000;
+
+static DATA_COMMANDS: [&str; 7] = [
+    "symbols",
+    "dictionary",
+    "pack",
+    "corpus",
+    "phrases",
+    "unicode",
+    "latex",
+];
+static COMMITTED_DATA_COMMANDS: [&str; 2] = ["symbols", "dictionary"];
+
+/// Command line arguments: any number of commands, `--option value` pairs and `--flag`s
+struct Args {
+    commands: Vec<String>,
+    options: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    const OPTIONS: [&'static str; 14] = [
+        "--math-class-file",
+        "--emoji-file",
+        "--dic-file",
+        "--aff-file",
+        "--freq-file",
+        "--locale",
+        "--words",
+        "--name",
+        "--data-dir",
+        "--regex",
+        "--corpus-dir",
+        "--weight",
+        "--min-count",
+        "--ngram-file",
+    ];
+    const FLAGS: [&'static str; 1] = ["--download"];
+
+    fn parse() -> Result<Args, Box<dyn error::Error>> {
+        let mut parsed = Args {
+            commands: Vec::new(),
+            options: HashMap::new(),
+            flags: HashSet::new(),
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if Args::OPTIONS.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))?;
+                parsed.options.insert(arg, value);
+            } else if Args::FLAGS.contains(&arg.as_str()) {
+                parsed.flags.insert(arg);
+            } else if arg.starts_with("--") {
+                return Err(format!("Unknown option {}", arg).into());
+            } else {
+                parsed.commands.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn contains(&self, command: &str) -> bool {
+        self.commands.iter().any(|arg| arg == command)
+    }
+
+    fn flag(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    fn option<'a>(&'a self, option: &str, default: &'a str) -> &'a str {
+        self.options
+            .get(option)
+            .map(|value| value.as_str())
+            .unwrap_or(default)
+    }
+}
+
+/// Saves the latest copy of a source file. This is the only network access, and only happens
+/// when --download is passed.
+fn download(url: &str, path: &str) -> Result<(), Box<dyn error::Error>> {
+    println!("Downloading {} to {}", url, path);
+    let mut reader = ureq::get(url).call()?.into_reader();
+    let mut file = File::create(path)?;
+    io::copy(&mut reader, &mut file)?;
+    Ok(())
 }
 
-fn math_symbol_shortcodes() -> Vec<(String, String)> {
-    let whitelist = io::BufReader::new(File::open("math_whitelist.txt").unwrap())
+/// Checksum of an input file, which is recorded in the bundles generated from it
+fn source_info(name: &str, path: &str) -> Result<SourceInfo, Box<dyn error::Error>> {
+    Ok(SourceInfo::new(name, &std::fs::read(path)?))
+}
+
+fn open_source_file(path: &str) -> Result<File, Box<dyn error::Error>> {
+    File::open(path).map_err(|err| {
+        format!(
+            "Could not open {}: {} (pass --download to fetch it)",
+            path, err
+        )
+        .into()
+    })
+}
+
+fn math_symbol_shortcodes(path: &str) -> Result<Vec<(String, String)>, Box<dyn error::Error>> {
+    let whitelist = io::BufReader::new(File::open("math_whitelist.txt")?)
         .lines()
-        .collect::<Result<HashSet<String>, _>>()
-        .unwrap();
+        .collect::<Result<HashSet<String>, _>>()?;
 
-    let reader = ureq::get("https://www.unicode.org/Public/math/revision-15/MathClassEx-15.txt")
-        .call()
-        .unwrap()
-        .into_reader();
+    let reader = io::BufReader::new(open_source_file(path)?);
 
     let rdr = csv::ReaderBuilder::new()
         .comment(Some(b'#'))
@@ -77,15 +204,13 @@ fn math_symbol_shortcodes() -> Vec<(String, String)> {
         .collect();
 
     println!("Found {} math symbols", output.len());
-    output
+    Ok(output)
 }
 
-fn github_emoji_shortcodes() -> Vec<(String, String)> {
-    let json: HashMap<String, String> = ureq::get("https://api.github.com/emojis")
-        .call()
-        .unwrap()
-        .into_json()
-        .unwrap();
+fn github_emoji_shortcodes(path: &str) -> Result<Vec<(String, String)>, Box<dyn error::Error>> {
+    //BTreeMap so that shortcodes are always processed in the same order
+    let json: BTreeMap<String, String> =
+        serde_json::from_reader(io::BufReader::new(open_source_file(path)?))?;
 
     //have to filter out bad URLs like
     // "https://github.githubassets.com/images/icons/emoji/bowtie.png?v8"
@@ -108,71 +233,188 @@ fn github_emoji_shortcodes() -> Vec<(String, String)> {
         })
         .collect::<Vec<(String, String)>>();
     println!("Found {} github emoji shortcodes", output.len());
-    output
+    Ok(output)
 }
 
-fn custom_shortcodes() -> Vec<(String, String)> {
-    //Read shortcode, sysmbol from custom_shortcodes.tsv
-    let reader = io::BufReader::new(
-        File::open("custom_shortcodes.tsv").expect("Failed to open custom_shortcodes.tsv"),
-    );
-    let mut rdr = csv::ReaderBuilder::new()
-        .delimiter(b'\t')
-        .has_headers(false)
-        .from_reader(reader);
-
-    let output: Vec<(String, String)> = rdr
-        .records()
-        .map(|result| {
-            let record = result.expect("Failed to parse a record in custom_shortcodes.tsv");
-            (record[0].to_string(), record[1].to_string())
-        })
-        .collect();
+fn custom_shortcodes() -> Result<Vec<ShortcodeEntry>, Box<dyn error::Error>> {
+    //Read shortcode, symbol and the optional extra columns from custom_shortcodes.tsv
+    let contents = std::fs::read_to_string("custom_shortcodes.tsv")?;
+    let output = parse_shortcode_entries(&contents)
+        .collect::<Result<Vec<ShortcodeEntry>, _>>()
+        .map_err(InvalidParseError::Custom)?;
 
     println!("Found {} custom shortcodes", output.len());
-    output
+    Ok(output)
 }
 
-fn write_symbols_and_shortcodes(
-    mut shortcodes_symbols: Vec<(String, String)>,
+/// Where a shortcode came from, in order of priority when several sources use the same shortcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Source {
+    Custom,
+    Github,
+    Math,
+}
+
+fn write_collision_report(
+    collisions: &[(&String, Vec<(u32, Source)>)],
+    symbols: &[SymbolInfo],
 ) -> Result<(), Box<dyn error::Error>> {
-    let writer = io::BufWriter::new(File::create("shortcodes.fst")?);
-    let mut map_builder = MapBuilder::new(writer)?;
+    let mut report = io::BufWriter::new(File::create("collisions.txt")?);
+    for (shortcode, ids) in collisions {
+        let ranked = ids
+            .iter()
+            .map(|(id, source)| format!("{} ({:?})", symbols[*id as usize].symbol, source))
+            .collect::<Vec<_>>();
+        writeln!(report, "{}\t{}", shortcode, ranked.join(", "))?;
+    }
+    Ok(())
+}
 
-    shortcodes_symbols.sort();
+/// Where generated data goes: written to disk, or when verifying, compared against the files that
+/// are already there. Everything must be generated deterministically for verification to work.
+struct DataOutput {
+    verify: bool,
+    mismatches: Vec<String>,
+    /// Files that couldn't be verified because their sources are missing
+    skipped: Vec<String>,
+}
 
-    let symbols = shortcodes_symbols
-        .iter()
-        .map(|(_shortcode, symbol)| symbol)
-        .collect::<HashSet<&String>>();
+impl DataOutput {
+    fn save(&mut self, filename: &str, data: &[u8]) -> Result<(), Box<dyn error::Error>> {
+        if !self.verify {
+            File::create(filename)?.write_all(data)?;
+        } else if std::fs::read(filename)
+            .map(|existing| existing == data)
+            .unwrap_or(false)
+        {
+            println!("{} matches the generated data", filename);
+        } else {
+            println!("{} does not match the generated data", filename);
+            self.mismatches.push(filename.to_string());
+        }
+        Ok(())
+    }
 
-    let symbol_id_map: HashMap<&String, u64> = symbols
-        .iter()
+    /// The sources of the committed data aren't in the repository, so when verifying all of it,
+    /// data whose sources haven't been downloaded is skipped rather than failing
+    fn skip_missing_sources(&mut self, filename: &str, sources: &[&str]) -> bool {
+        let missing: Vec<&str> = sources
+            .iter()
+            .copied()
+            .filter(|path| !Path::new(path).exists())
+            .collect();
+        if missing.is_empty() {
+            return false;
+        }
+        println!("Skipping {}: {} not found", filename, missing.join(", "));
+        self.skipped.push(filename.to_string());
+        true
+    }
+}
+
+fn write_symbols_and_shortcodes(
+    mut entries: Vec<(Source, ShortcodeEntry)>,
+    sources: Vec<SourceInfo>,
+    output: &mut DataOutput,
+) -> Result<(), Box<dyn error::Error>> {
+    //everything below relies on higher priority sources coming first
+    entries.sort_by_key(|(source, _entry)| *source);
+
+    //symbols with more than one shortcode keep the first category/description they're given
+    let mut symbol_infos: BTreeMap<&String, SymbolInfo> = BTreeMap::new();
+    for (_source, entry) in entries.iter() {
+        let info = symbol_infos
+            .entry(&entry.symbol)
+            .or_insert_with(|| SymbolInfo::new(entry.symbol.clone()));
+        info.category = info.category.take().or_else(|| entry.category.clone());
+        info.description = info
+            .description
+            .take()
+            .or_else(|| entry.description.clone());
+        for shortcode in entry.shortcodes() {
+            if !info.shortcodes.contains(shortcode) {
+                info.shortcodes.push(shortcode.clone());
+            }
+        }
+    }
+
+    let symbol_id_map: HashMap<&String, u32> = symbol_infos
+        .keys()
         .enumerate()
-        .map(|(idx, symbol)| (*symbol, idx as u64))
+        .map(|(idx, symbol)| (*symbol, idx as u32))
         .collect();
+    let symbols: Vec<SymbolInfo> = symbol_infos.into_values().collect();
+
+    //BTreeMap so keys are in lexographical order to build the FST
+    let mut key_ids: BTreeMap<String, Vec<(u32, Source)>> = BTreeMap::new();
+    for (source, entry) in entries.iter() {
+        let id = symbol_id_map[&entry.symbol];
+        for key in entry.keys() {
+            let ids = key_ids.entry(key).or_default();
+            if !ids.iter().any(|(existing, _source)| *existing == id) {
+                ids.push((id, *source));
+            }
+        }
+    }
 
-    for (shortcode, symbol) in shortcodes_symbols.iter() {
-        map_builder.insert(shortcode, *symbol_id_map.get(symbol).unwrap())?;
+    let collisions: Vec<(&String, Vec<(u32, Source)>)> = key_ids
+        .iter()
+        .filter(|(key, ids)| ids.len() > 1 && key_shortcode(key) == key.as_str())
+        .map(|(key, ids)| (key, ids.clone()))
+        .collect();
+    if collisions.is_empty() {
+        println!("No shortcode collisions detected");
+    } else {
+        println!(
+            "{} shortcodes belong to several symbols, see collisions.txt",
+            collisions.len()
+        );
+        if !output.verify {
+            write_collision_report(&collisions, &symbols)?;
+        }
     }
 
-    // Finish construction of the map and flush its contents to disk.
-    map_builder.finish()?;
+    //keys with the same symbols share the same list
+    let mut key_symbols: Vec<Vec<u32>> = Vec::new();
+    let mut key_symbols_idx: HashMap<Vec<u32>, u64> = HashMap::new();
+
+    let mut map_builder = MapBuilder::memory();
+    for (key, ids) in key_ids.iter() {
+        let ids: Vec<u32> = ids.iter().map(|(id, _source)| *id).collect();
+        let idx = *key_symbols_idx.entry(ids.clone()).or_insert_with(|| {
+            key_symbols.push(ids);
+            (key_symbols.len() - 1) as u64
+        });
+        map_builder.insert(key, idx)?;
+    }
 
-    let mut symbol_file = File::create("symbols.bin")?;
-    symbol_file.write_all(&bincode::serialize(&symbols)?)?;
+    let symbol_data = SymbolData {
+        symbols,
+        key_symbols,
+    };
+    output.save(
+        SYMBOLS_BUNDLE,
+        &write_bundle(
+            sources,
+            &[
+                (SHORTCODES_SECTION, &map_builder.into_inner()?),
+                (SYMBOLS_SECTION, &bincode::serialize(&symbol_data)?),
+            ],
+        ),
+    )?;
 
     println!(
-        "Wrote {shortcodes} shortcodes for {symbols} symbols",
-        shortcodes = shortcodes_symbols.len(),
-        symbols = symbols.len()
+        "Generated {keys} keys for {shortcodes} shortcodes and {symbols} symbols",
+        keys = key_ids.len(),
+        shortcodes = entries.len(),
+        symbols = symbol_data.symbols.len()
     );
 
     Ok(())
 }
 
-fn load_word_freq_data() -> Result<HashMap<String, u64>, Box<dyn error::Error>> {
-    let lines = io::BufReader::new(File::open("count_1w.txt")?)
+fn load_word_freq_data(path: &str) -> Result<HashMap<String, u64>, Box<dyn error::Error>> {
+    let lines = io::BufReader::new(File::open(path)?)
         .lines()
         .collect::<Result<Vec<_>, _>>()?;
 
@@ -203,19 +445,40 @@ fn load_word_freq_data() -> Result<HashMap<String, u64>, Box<dyn error::Error>>
         .collect::<Result<HashMap<String, u64>, InvalidParseError>>()?)
 }
 
-fn process_dictionary() -> Result<(), Box<dyn error::Error>> {
-    let writer = io::BufWriter::new(File::create("dictionary.fst")?);
-    let mut map_builder = MapBuilder::new(writer)?;
+/// Every word in a hunspell dictionary, with its affix rules applied
+fn hunspell_words(dic_path: &str, aff_path: &str) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let read =
+        |path: &str| std::fs::read(path).map_err(|err| format!("Could not open {}: {}", path, err));
+    let words = hunspell::expand_dictionary(&read(dic_path)?, &read(aff_path)?).map_err(|err| {
+        let path = if err.file == hunspell::AFFIX_FILE {
+            aff_path
+        } else {
+            dic_path
+        };
+        InvalidParseError::Hunspell(path.to_string(), err)
+    })?;
+    println!("Expanded {} to {} words", dic_path, words.len());
+    Ok(words)
+}
 
-    let mut lines = io::BufReader::new(File::open("hunspell_US.txt")?)
-        .lines()
-        .map(|line_res| line_res.map(|line| line.to_lowercase()))
-        .collect::<Result<Vec<_>, _>>()?;
+fn process_dictionary(
+    locale: &str,
+    dic_path: &str,
+    aff_path: &str,
+    freq_path: &str,
+    output: &mut DataOutput,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut map_builder = MapBuilder::memory();
+
+    let mut lines: Vec<String> = hunspell_words(dic_path, aff_path)?
+        .into_iter()
+        .map(|word| word.to_lowercase())
+        .collect();
 
     //must be in lexographical order to build the FST
     lines.sort();
     lines.dedup();
-    let word_freq = load_word_freq_data()?;
+    let word_freq = load_word_freq_data(freq_path)?;
 
     let mut words_without_freq = 0;
 
@@ -231,7 +494,15 @@ fn process_dictionary() -> Result<(), Box<dyn error::Error>> {
 
     let words_with_freq = lines.len() - words_without_freq;
 
-    map_builder.finish()?;
+    let sources = vec![
+        source_info("hunspell dictionary", dic_path)?,
+        source_info("hunspell affixes", aff_path)?,
+        source_info("word frequencies", freq_path)?,
+    ];
+    output.save(
+        &dictionary_bundle(locale),
+        &write_bundle(sources, &[(DICTIONARY_SECTION, &map_builder.into_inner()?)]),
+    )?;
     println!(
         "Wrote {entries} dictionary entries, of which {with_freq} had frequency ({perc:.2}%)",
         entries = lines.len(),
@@ -241,40 +512,494 @@ fn process_dictionary() -> Result<(), Box<dyn error::Error>> {
     Ok(())
 }
 
-fn main() -> Result<(), Box<dyn error::Error>> {
-    let args: HashSet<String> = env::args().collect();
+/// Builds a domain word pack from a list of words, each optionally followed by a tab and its count
+fn process_pack(
+    words_path: &str,
+    name: &str,
+    output: &mut DataOutput,
+) -> Result<(), Box<dyn error::Error>> {
+    let contents = std::fs::read_to_string(words_path)
+        .map_err(|err| format!("Could not open {}: {}", words_path, err))?;
+
+    //BTreeMap so words are in lexographical order to build the FST
+    let mut words: BTreeMap<String, u64> = BTreeMap::new();
+    for entry in parse_word_counts(&contents) {
+        let (word, count) =
+            entry.map_err(|err| InvalidParseError::Words(words_path.to_string(), err))?;
+        //words listed more than once keep their highest count
+        let existing = words.entry(word).or_insert(0);
+        *existing = (*existing).max(count);
+    }
 
-    if args.contains("symbols") {
-        println!("-- Processing symbols and shortcodes --");
-        println!("Fetching math symbols");
-        let math_shortcodes = math_symbol_shortcodes();
+    let mut map_builder = MapBuilder::memory();
+    for (word, count) in words.iter() {
+        map_builder.insert(word, *count)?;
+    }
+    let filename = pack_bundle(name);
+    output.save(
+        &filename,
+        &write_bundle(
+            vec![source_info("word list", words_path)?],
+            &[(DICTIONARY_SECTION, &map_builder.into_inner()?)],
+        ),
+    )?;
+    println!("Wrote {} words to {}", words.len(), filename);
+    Ok(())
+}
 
-        println!("Fetching shortcodes from github");
-        let github_shortcodes = github_emoji_shortcodes();
+/// Builds an overlay pack from the words in a directory of text and markdown files. Each word's
+/// count is its base frequency plus `weight` times its corpus count, scaled so that the corpus has
+/// as many words in total as the base frequencies. Words seen fewer than `min_count` times are
+/// left out.
+fn process_corpus(
+    corpus_dir: &str,
+    freq_path: &str,
+    weight: f64,
+    min_count: u64,
+    name: &str,
+    output: &mut DataOutput,
+) -> Result<(), Box<dyn error::Error>> {
+    let files = corpus::corpus_files(Path::new(corpus_dir))
+        .map_err(|err| format!("Could not read {}: {}", corpus_dir, err))?;
+
+    //BTreeMap so words are in lexographical order to build the FST
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    //the corpus is checksummed a file at a time rather than kept in memory
+    let mut corpus_hasher = crc32fast::Hasher::new();
+    for file in files.iter() {
+        let contents = std::fs::read(file)?;
+        corpus::count_words(&String::from_utf8_lossy(&contents), &mut counts);
+        //relative paths, so the checksum doesn't depend on where the corpus is checked out
+        let relative = file.strip_prefix(corpus_dir).unwrap_or(file);
+        corpus_hasher.update(relative.to_string_lossy().as_bytes());
+        corpus_hasher.update(&contents);
+    }
+    let corpus_total: u64 = counts.values().sum();
+    counts.retain(|_word, count| *count >= min_count);
+    if counts.is_empty() {
+        return Err(format!(
+            "No words seen at least {} times in {} files in {}",
+            min_count,
+            files.len(),
+            corpus_dir
+        )
+        .into());
+    }
 
-        let custom_shortcodes = custom_shortcodes();
-        let all_symbols = [math_shortcodes, github_shortcodes, custom_shortcodes].concat();
+    let word_freq = load_word_freq_data(freq_path)?;
+    let base_total: u64 = word_freq.values().sum();
+    let scale = weight * base_total as f64 / corpus_total as f64;
 
-        let shortcode_set = all_symbols
-            .iter()
-            .map(|(shortcode, _)| shortcode)
-            .collect::<HashSet<&String>>();
+    let mut map_builder = MapBuilder::memory();
+    for (word, count) in counts.iter() {
+        let base = word_freq.get(word).copied().unwrap_or(0);
+        map_builder.insert(
+            word,
+            base.saturating_add((*count as f64 * scale).round() as u64),
+        )?;
+    }
+    let filename = pack_bundle(name);
+    output.save(
+        &filename,
+        &write_bundle(
+            vec![
+                SourceInfo {
+                    name: "corpus".to_string(),
+                    crc32: corpus_hasher.finalize(),
+                },
+                source_info("word frequencies", freq_path)?,
+            ],
+            &[(DICTIONARY_SECTION, &map_builder.into_inner()?)],
+        ),
+    )?;
+    println!(
+        "Wrote {} words from {} files ({} words in total) to {}",
+        counts.len(),
+        files.len(),
+        corpus_total,
+        filename
+    );
+    Ok(())
+}
 
-        if shortcode_set.len() != all_symbols.len() {
-            return Err("Shortcode collision detected".into());
-        } else {
-            println!("No shortcode collisions detected");
+/// Builds the phrase completions for a locale from a list of n-gram counts, each a phrase of
+/// several words optionally followed by a tab and its count. Phrases with a count below `min_count`
+/// and n-grams that aren't only words (like those with numbers or sentence markers) are left out.
+fn process_phrases(
+    locale: &str,
+    ngram_path: &str,
+    min_count: u64,
+    output: &mut DataOutput,
+) -> Result<(), Box<dyn error::Error>> {
+    let contents = std::fs::read_to_string(ngram_path)
+        .map_err(|err| format!("Could not open {}: {}", ngram_path, err))?;
+
+    //BTreeMap so phrases are in lexographical order to build the FST
+    let mut phrases: BTreeMap<String, u64> = BTreeMap::new();
+    for entry in parse_word_counts(&contents) {
+        let (ngram, count) =
+            entry.map_err(|err| InvalidParseError::Words(ngram_path.to_string(), err))?;
+        let words: Vec<&str> = ngram.split_whitespace().collect();
+        let is_phrase = words.len() > 1
+            && words
+                .iter()
+                .all(|word| word.chars().all(|c| c.is_alphabetic() || c == '\''));
+        if is_phrase && count >= min_count {
+            let existing = phrases.entry(words.join(" ")).or_insert(0);
+            *existing = (*existing).max(count);
+        }
+    }
+
+    let mut map_builder = MapBuilder::memory();
+    for (phrase, count) in phrases.iter() {
+        map_builder.insert(phrase, *count)?;
+    }
+    let filename = phrases_bundle(locale);
+    output.save(
+        &filename,
+        &write_bundle(
+            vec![source_info("n-gram counts", ngram_path)?],
+            &[(DICTIONARY_SECTION, &map_builder.into_inner()?)],
+        ),
+    )?;
+    println!("Wrote {} phrases to {}", phrases.len(), filename);
+    Ok(())
+}
+
+fn process_unicode_names(output: &mut DataOutput) -> Result<(), Box<dyn error::Error>> {
+    //https://www.unicode.org/reports/tr44/#UnicodeData.txt
+    let reader = io::BufReader::new(File::open("UnicodeData.txt")?);
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .has_headers(false)
+        .quoting(false)
+        .from_reader(reader);
+
+    let mut name_count = 0;
+    let mut keys: Vec<(String, u64)> = Vec::new();
+    for result in rdr.into_records() {
+        let record = result?;
+        //skip <control> characters and the <CJK Ideograph, First> style range markers
+        if record[1].starts_with('<') {
+            continue;
+        }
+
+        let symbol = parse_unicode(&record[0]).map_err(InvalidParseError::Unicode)?;
+        let name = record[1].to_lowercase();
+        //one key per word in the name, so characters can be found from any word in their name
+        for start in name_word_starts(&name) {
+            keys.push((
+                format!("{}{}{}", &name[start..], NAME_SEPARATOR, name),
+                symbol as u64,
+            ));
+        }
+        name_count += 1;
+    }
+
+    //must be in lexographical order to build the FST
+    keys.sort();
+
+    let mut map_builder = MapBuilder::memory();
+    for (key, codepoint) in keys.iter() {
+        map_builder.insert(key, *codepoint)?;
+    }
+    output.save(
+        UNICODE_NAMES_BUNDLE,
+        &write_bundle(
+            vec![source_info("UnicodeData.txt", "UnicodeData.txt")?],
+            &[(NAMES_SECTION, &map_builder.into_inner()?)],
+        ),
+    )?;
+
+    println!(
+        "Wrote {keys} name keys for {names} named characters",
+        keys = keys.len(),
+        names = name_count
+    );
+    Ok(())
+}
+
+//unicode-math names for styled letters, and the LaTeX commands that produce the same letters
+static LATEX_STYLE_COMMANDS: [(&str, &str); 8] = [
+    ("Bbb", "mathbb"),
+    ("mbf", "mathbf"),
+    ("mit", "mathit"),
+    ("mscr", "mathscr"),
+    ("mscr", "mathcal"),
+    ("mfrak", "mathfrak"),
+    ("msans", "mathsf"),
+    ("mtt", "mathtt"),
+];
+
+fn latex_style_aliases(command: &str) -> Vec<String> {
+    LATEX_STYLE_COMMANDS
+        .iter()
+        .filter_map(|(prefix, style_command)| {
+            command
+                .strip_prefix('\\')
+                .and_then(|name| name.strip_prefix(prefix))
+                .filter(|letter| {
+                    letter.len() == 1 && letter.chars().all(|c| c.is_ascii_alphabetic())
+                })
+                .map(|letter| format!("\\{}{{{}}}", style_command, letter))
+        })
+        .collect()
+}
+
+fn parse_unicode_math_line(line: &str) -> Result<Option<(String, char)>, InvalidParseError> {
+    //\UnicodeMathSymbol{"003B1}{\alpha                    }{\mathalpha}{small alpha, greek}%
+    let fields = match line.strip_prefix("\\UnicodeMathSymbol{\"") {
+        Some(fields) => fields,
+        None => return Ok(None),
+    };
+
+    let mut split_fields = fields.split("}{");
+    match (split_fields.next(), split_fields.next()) {
+        (Some(codepoint), Some(command)) => {
+            let symbol = parse_unicode(codepoint).map_err(InvalidParseError::Unicode)?;
+            Ok(Some((command.trim().to_string(), symbol)))
         }
+        _ => Err(InvalidParseError::Latex(line.to_owned())),
+    }
+}
 
-        println!("Writing symbols and shortcodes to files");
-        write_symbols_and_shortcodes(all_symbols)?;
+fn process_latex_commands(output: &mut DataOutput) -> Result<(), Box<dyn error::Error>> {
+    let mut commands: HashMap<String, char> = HashMap::new();
+    //https://github.com/latex3/unicode-math/blob/master/unicode-math-table.tex
+    for line in io::BufReader::new(File::open("unicode-math-table.tex")?).lines() {
+        if let Some((command, symbol)) = parse_unicode_math_line(&line?)? {
+            for alias in latex_style_aliases(&command) {
+                commands.entry(alias).or_insert(symbol);
+            }
+            commands.insert(command, symbol);
+        }
+    }
+    println!("Found {} unicode-math commands", commands.len());
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .has_headers(false)
+        .from_reader(io::BufReader::new(File::open("latex_aliases.tsv")?));
+    for result in rdr.records() {
+        let record = result?;
+        match commands.get(&record[1]) {
+            Some(symbol) => {
+                let symbol = *symbol;
+                commands.entry(record[0].to_string()).or_insert(symbol);
+            }
+            None => println!(
+                "Skipping alias {} for unknown command {}",
+                &record[0], &record[1]
+            ),
+        }
+    }
+
+    let mut sorted_commands: Vec<(String, char)> = commands.into_iter().collect();
+    //must be in lexographical order to build the FST
+    sorted_commands.sort();
+
+    let mut map_builder = MapBuilder::memory();
+    for (command, symbol) in sorted_commands.iter() {
+        map_builder.insert(command, *symbol as u64)?;
+    }
+    output.save(
+        LATEX_COMMANDS_BUNDLE,
+        &write_bundle(
+            vec![
+                source_info("unicode-math-table.tex", "unicode-math-table.tex")?,
+                source_info("latex_aliases.tsv", "latex_aliases.tsv")?,
+            ],
+            &[(COMMANDS_SECTION, &map_builder.into_inner()?)],
+        ),
+    )?;
+
+    println!("Wrote {} LaTeX commands", sorted_commands.len());
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn error::Error>> {
+    let args = Args::parse()?;
+    if args.commands.first().map(String::as_str) == Some("inspect") {
+        return inspect::inspect(
+            &args.commands[1..],
+            args.option("--data-dir", "."),
+            args.option("--locale", DEFAULT_LOCALE),
+            args.options.get("--regex").map(String::as_str),
+        );
+    }
+
+    let mut output = DataOutput {
+        verify: args.contains("verify"),
+        mismatches: Vec::new(),
+        skipped: Vec::new(),
+    };
+    //verify on its own checks all of the data committed to the repository
+    let verify_committed =
+        output.verify && !DATA_COMMANDS.iter().any(|command| args.contains(command));
+    let run = |command: &str| {
+        args.contains(command) || (verify_committed && COMMITTED_DATA_COMMANDS.contains(&command))
+    };
+
+    let math_class_file = args.option("--math-class-file", DEFAULT_MATH_CLASS_FILE);
+    let emoji_file = args.option("--emoji-file", DEFAULT_EMOJI_FILE);
+    let skip_symbols = verify_committed
+        && !args.flag("--download")
+        && output.skip_missing_sources(SYMBOLS_BUNDLE, &[math_class_file, emoji_file]);
+    if run("symbols") && !skip_symbols {
+        println!("-- Processing symbols and shortcodes --");
+        if args.flag("--download") {
+            download(MATH_CLASS_URL, math_class_file)?;
+            download(GITHUB_EMOJI_URL, emoji_file)?;
+        }
+
+        println!("Reading math symbols from {}", math_class_file);
+        let math_shortcodes = math_symbol_shortcodes(math_class_file)?;
+
+        println!("Reading github shortcodes from {}", emoji_file);
+        let github_shortcodes = github_emoji_shortcodes(emoji_file)?;
+
+        let custom_shortcodes = custom_shortcodes()?;
+        let all_symbols: Vec<(Source, ShortcodeEntry)> = math_shortcodes
+            .into_iter()
+            .map(|(shortcode, symbol)| (Source::Math, ShortcodeEntry::new(shortcode, symbol)))
+            .chain(github_shortcodes.into_iter().map(|(shortcode, symbol)| {
+                (Source::Github, ShortcodeEntry::new(shortcode, symbol))
+            }))
+            .chain(
+                custom_shortcodes
+                    .into_iter()
+                    .map(|entry| (Source::Custom, entry)),
+            )
+            .collect();
+
+        let sources = vec![
+            source_info("MathClassEx", math_class_file)?,
+            source_info("math_whitelist.txt", "math_whitelist.txt")?,
+            source_info("github emojis", emoji_file)?,
+            source_info("custom_shortcodes.tsv", "custom_shortcodes.tsv")?,
+        ];
+
+        println!("Generating symbols and shortcodes");
+        write_symbols_and_shortcodes(all_symbols, sources, &mut output)?;
         println!("-- Done processing symbols and shortcodes --");
     }
-    if args.contains("dictionary") {
+    let locale = args.option("--locale", DEFAULT_LOCALE);
+    let default_dic_file = format!("{}.dic", locale);
+    let dic_file = args.option("--dic-file", &default_dic_file);
+    let default_aff_file = Path::new(dic_file)
+        .with_extension("aff")
+        .to_string_lossy()
+        .into_owned();
+    let aff_file = args.option("--aff-file", &default_aff_file);
+    let freq_file = args.option("--freq-file", DEFAULT_FREQ_FILE);
+    let skip_dictionary = verify_committed
+        && output
+            .skip_missing_sources(&dictionary_bundle(locale), &[dic_file, aff_file, freq_file]);
+    if run("dictionary") && !skip_dictionary {
         println!("-- Processing dictionary --");
-        process_dictionary()?;
+        process_dictionary(locale, dic_file, aff_file, freq_file, &mut output)?;
         println!("-- Done processing dictionary --");
     }
+    if run("pack") {
+        println!("-- Processing word pack --");
+        let words_file = args
+            .options
+            .get("--words")
+            .ok_or("pack needs a word list, given with --words <path>")?;
+        let default_name = Path::new(words_file)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = args.option("--name", &default_name);
+        process_pack(words_file, name, &mut output)?;
+        println!("-- Done processing word pack --");
+    }
+    if run("corpus") {
+        println!("-- Processing corpus --");
+        let corpus_dir = args
+            .options
+            .get("--corpus-dir")
+            .ok_or("corpus needs a directory of text files, given with --corpus-dir <dir>")?;
+        let weight = args
+            .option("--weight", "1")
+            .parse::<f64>()
+            .ok()
+            .filter(|weight| *weight >= 0.0)
+            .ok_or("--weight must be a number of at least 0")?;
+        let min_count = args
+            .option("--min-count", "2")
+            .parse::<u64>()
+            .map_err(|err| format!("Invalid --min-count: {}", err))?;
+        let name = args.option("--name", DEFAULT_CORPUS_NAME);
+        process_corpus(corpus_dir, freq_file, weight, min_count, name, &mut output)?;
+        println!("-- Done processing corpus --");
+    }
+    if run("phrases") {
+        println!("-- Processing phrases --");
+        let ngram_file = args.option("--ngram-file", DEFAULT_NGRAM_FILE);
+        let default_min_count = DEFAULT_PHRASE_MIN_COUNT.to_string();
+        let min_count = args
+            .option("--min-count", &default_min_count)
+            .parse::<u64>()
+            .map_err(|err| format!("Invalid --min-count: {}", err))?;
+        process_phrases(locale, ngram_file, min_count, &mut output)?;
+        println!("-- Done processing phrases --");
+    }
+    if run("unicode") {
+        println!("-- Processing unicode names --");
+        process_unicode_names(&mut output)?;
+        println!("-- Done processing unicode names --");
+    }
+    if run("latex") {
+        println!("-- Processing LaTeX commands --");
+        process_latex_commands(&mut output)?;
+        println!("-- Done processing LaTeX commands --");
+    }
+
+    if !output.skipped.is_empty() {
+        println!(
+            "Could not verify {} because the files they're generated from are missing. See \
+             \"Generating dictionary data\" in the README for how to get them.",
+            output.skipped.join(", ")
+        );
+    }
+    if !output.mismatches.is_empty() {
+        return Err(format!(
+            "Generated data does not match {}",
+            output.mismatches.join(", ")
+        )
+        .into());
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{latex_style_aliases, parse_unicode_math_line};
+
+    #[test]
+    fn unicode_math_lines() {
+        let alpha = r#"\UnicodeMathSymbol{"003B1}{\alpha                    }{\mathalpha}{small alpha, greek}%"#;
+        assert_eq!(
+            parse_unicode_math_line(alpha).unwrap(),
+            Some(("\\alpha".to_string(), 'α'))
+        );
+
+        let (command, symbol) = parse_unicode_math_line(
+            r#"\UnicodeMathSymbol{"1D539}{\BbbB                     }{\mathalpha}{matrix}%"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(symbol, '𝔹');
+        assert_eq!(latex_style_aliases(&command), vec!["\\mathbb{B}"]);
+
+        assert_eq!(
+            parse_unicode_math_line(&format!("%{}", alpha)).unwrap(),
+            None
+        );
+        assert_eq!(parse_unicode_math_line("").unwrap(), None);
+        assert!(parse_unicode_math_line(r#"\UnicodeMathSymbol{"003B1}"#).is_err());
+        assert!(parse_unicode_math_line(r#"\UnicodeMathSymbol{"XYZ}{\alpha}{}{}%"#).is_err());
+    }
+}